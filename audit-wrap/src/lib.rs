@@ -0,0 +1,80 @@
+//! `#[audit_wrap]`: instruments every method of a `Guest` impl block with an
+//! audit record.
+//!
+//! Each method body is moved into a closure passed to
+//! `crate::audit::record`, together with the WIT export name (the method
+//! name in kebab-case) and a hash of the arguments taken before the body
+//! runs. The `audit` module of the component decides what is recorded and
+//! where.
+//!
+//! Each export call is one logical request, so the method first starts a
+//! fresh cost ledger with `crate::request_budget::reset`, before any
+//! upstream call can be admitted.
+//!
+//! The macro also emits `GUEST_EXPORTS`, the export names it instrumented,
+//! so the component can report its exports without a hand-kept list.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, FnArg, ImplItem, ItemImpl, Pat};
+
+#[proc_macro_attribute]
+pub fn audit_wrap(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(
+            proc_macro2::TokenStream::from(attr).span(),
+            "audit_wrap takes no arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let mut block = parse_macro_input!(item as ItemImpl);
+    let mut exports = Vec::new();
+    for item in &mut block.items {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+
+        let mut args = Vec::new();
+        for input in &method.sig.inputs {
+            match input {
+                FnArg::Typed(typed) => match &*typed.pat {
+                    Pat::Ident(ident) => args.push(ident.ident.clone()),
+                    other => {
+                        return syn::Error::new(
+                            other.span(),
+                            "audit_wrap needs plain identifiers as argument patterns",
+                        )
+                        .to_compile_error()
+                        .into()
+                    }
+                },
+                FnArg::Receiver(receiver) => {
+                    return syn::Error::new(
+                        receiver.span(),
+                        "audit_wrap only supports methods without a receiver",
+                    )
+                    .to_compile_error()
+                    .into()
+                }
+            }
+        }
+
+        let export = method.sig.ident.to_string().replace('_', "-");
+        exports.push(export.clone());
+        let body = &method.block;
+        method.block = syn::parse_quote!({
+            crate::request_budget::reset();
+            let input_hash = crate::audit::hash(&(#(&#args,)*));
+            crate::audit::record(#export, input_hash, move || #body)
+        });
+    }
+
+    quote! {
+        #block
+
+        pub(crate) const GUEST_EXPORTS: &[&str] = &[#(#exports),*];
+    }
+    .into()
+}
@@ -0,0 +1,82 @@
+//! A/B test variants for marketing copy.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+/// Upper bound on variants generated per call.
+const MAX_VARIANTS: u8 = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct AbVariant {
+    pub name: String,
+    pub copy: String,
+    pub rationale: String,
+    pub hypothesis_tested: String,
+}
+
+#[derive(Deserialize)]
+struct AbVariants {
+    variants: Vec<AbVariant>,
+}
+
+const INSTRUCTIONS: &str = "You are a conversion optimization specialist. \
+Rewrite the original copy into distinct experiment variants. Each variant must change a different aspect \
+(for example headline, call to action, tone, length, or social proof) so results can be attributed, \
+state which part of the hypothesis it tests, and explain why it might outperform the original.";
+
+/// Asks the model for `n` copy variants testing `hypothesis`.
+pub async fn generate_ab_variants(
+    original_copy: String,
+    hypothesis: String,
+    n: u8,
+) -> Result<Vec<AbVariant>> {
+    ensure!(
+        (1..=MAX_VARIANTS).contains(&n),
+        "n must be between 1 and {MAX_VARIANTS}, got {n}"
+    );
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "variants": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "copy": { "type": "string" },
+                        "rationale": { "type": "string" },
+                        "hypothesis_tested": { "type": "string" }
+                    },
+                    "required": ["name", "copy", "rationale", "hypothesis_tested"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["variants"],
+        "additionalProperties": false
+    });
+
+    let input = format!(
+        "Number of variants: {n}\nHypothesis: {hypothesis}\nOriginal copy:\n{original_copy}"
+    );
+    let mut variants: AbVariants =
+        structured::request("ab_variants", INSTRUCTIONS, &input, schema).await?;
+
+    variants.variants.truncate(n.into());
+    Ok(variants.variants)
+}
+
+impl From<AbVariant> for wit::AbVariant {
+    fn from(variant: AbVariant) -> Self {
+        Self {
+            name: variant.name,
+            copy: variant.copy,
+            rationale: variant.rationale,
+            hypothesis_tested: variant.hypothesis_tested,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Monitoring alert rules generated from a system description and its SLA.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct SlaConfig {
+    pub availability_percent: f32,
+    pub p99_latency_ms: u32,
+    pub error_rate_percent: f32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub condition: String,
+    pub threshold: String,
+    pub severity: Severity,
+    pub runbook_url: String,
+}
+
+#[derive(Deserialize)]
+struct AlertRules {
+    alerts: Vec<AlertRule>,
+}
+
+const INSTRUCTIONS: &str = "You are a site reliability engineer. \
+Given a system description and its SLA, write alerting rules that detect SLA breaches early. \
+Each condition must be a valid Prometheus alerting expression (PromQL), the threshold must restate \
+the numeric limit the expression compares against, and runbook_url should point to a plausible \
+runbook path for the alert.";
+
+/// Asks the model for Prometheus-style alert rules covering `sla`.
+pub async fn generate_alerts(system_description: String, sla: SlaConfig) -> Result<Vec<AlertRule>> {
+    ensure!(
+        (0.0..=100.0).contains(&sla.availability_percent),
+        "availability_percent must be between 0 and 100"
+    );
+    ensure!(
+        (0.0..=100.0).contains(&sla.error_rate_percent),
+        "error_rate_percent must be between 0 and 100"
+    );
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "alerts": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "condition": { "type": "string" },
+                        "threshold": { "type": "string" },
+                        "severity": { "type": "string", "enum": ["info", "warning", "critical"] },
+                        "runbook_url": { "type": "string" }
+                    },
+                    "required": ["name", "condition", "threshold", "severity", "runbook_url"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["alerts"],
+        "additionalProperties": false
+    });
+
+    let input = format!(
+        "System: {system_description}\n\
+         SLA: availability {}%, p99 latency {} ms, error rate {}%",
+        sla.availability_percent, sla.p99_latency_ms, sla.error_rate_percent
+    );
+    let rules: AlertRules =
+        structured::request("alert_rules", INSTRUCTIONS, &input, schema).await?;
+
+    Ok(rules.alerts)
+}
+
+impl From<Severity> for wit::Severity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Info => Self::Info,
+            Severity::Warning => Self::Warning,
+            Severity::Critical => Self::Critical,
+        }
+    }
+}
+
+impl From<AlertRule> for wit::AlertRule {
+    fn from(rule: AlertRule) -> Self {
+        Self {
+            name: rule.name,
+            condition: rule.condition,
+            threshold: rule.threshold,
+            severity: rule.severity.into(),
+            runbook_url: rule.runbook_url,
+        }
+    }
+}
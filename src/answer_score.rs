@@ -0,0 +1,122 @@
+//! Rubric-based scoring of technical interview answers.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug)]
+pub struct AnswerScore {
+    /// Mean of the rubric scores, from 0 to 10.
+    pub total_score: f32,
+    pub rubric_scores: Vec<(String, f32)>,
+    pub feedback: String,
+    pub missing_concepts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CriterionScore {
+    criterion: String,
+    score: f32,
+}
+
+#[derive(Deserialize)]
+struct ScoredAnswer {
+    rubric_scores: Vec<CriterionScore>,
+    feedback: String,
+    missing_concepts: Vec<String>,
+}
+
+const INSTRUCTIONS: &str = "You are a senior engineer grading a technical screening answer. \
+Score the answer against each rubric criterion independently from 0 to 10, in the order given, repeating each criterion verbatim. \
+Judge correctness and depth, not length or confidence. Give concise, actionable feedback \
+and list the concepts a strong answer would have covered but this one missed.";
+
+/// Scores `answer` to `question` on each `rubric` criterion, weighting all
+/// criteria equally.
+pub async fn score_technical_answer(
+    question: String,
+    answer: String,
+    rubric: Vec<String>,
+) -> Result<AnswerScore> {
+    ensure!(
+        rubric.len() >= 2,
+        "rubric must have at least 2 criteria, got {}",
+        rubric.len()
+    );
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "rubric_scores": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "criterion": { "type": "string" },
+                        "score": { "type": "number", "minimum": 0, "maximum": 10 }
+                    },
+                    "required": ["criterion", "score"],
+                    "additionalProperties": false
+                }
+            },
+            "feedback": { "type": "string" },
+            "missing_concepts": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["rubric_scores", "feedback", "missing_concepts"],
+        "additionalProperties": false
+    });
+
+    let criteria = rubric
+        .iter()
+        .enumerate()
+        .map(|(index, criterion)| format!("{}. {criterion}", index + 1))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let input = format!("Question:\n{question}\n\nAnswer:\n{answer}\n\nRubric:\n{criteria}");
+    let scored: ScoredAnswer =
+        structured::request("answer_score", INSTRUCTIONS, &input, schema).await?;
+
+    ensure!(
+        scored.rubric_scores.len() == rubric.len(),
+        "expected {} rubric scores, got {}",
+        rubric.len(),
+        scored.rubric_scores.len()
+    );
+    for score in &scored.rubric_scores {
+        ensure!(
+            (0.0..=10.0).contains(&score.score),
+            "score for {:?} must be between 0 and 10, got {}",
+            score.criterion,
+            score.score
+        );
+    }
+
+    // Keyed by the caller's criteria so the model's echo cannot rename them.
+    let rubric_scores: Vec<(String, f32)> = rubric
+        .into_iter()
+        .zip(scored.rubric_scores)
+        .map(|(criterion, score)| (criterion, score.score))
+        .collect();
+    let total_score =
+        rubric_scores.iter().map(|(_, score)| score).sum::<f32>() / rubric_scores.len() as f32;
+
+    Ok(AnswerScore {
+        total_score,
+        rubric_scores,
+        feedback: scored.feedback,
+        missing_concepts: scored.missing_concepts,
+    })
+}
+
+impl From<AnswerScore> for wit::AnswerScore {
+    fn from(score: AnswerScore) -> Self {
+        Self {
+            total_score: score.total_score,
+            rubric_scores: score.rubric_scores,
+            feedback: score.feedback,
+            missing_concepts: score.missing_concepts,
+        }
+    }
+}
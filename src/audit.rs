@@ -0,0 +1,85 @@
+//! Audit records for every export call, emitted by `#[audit_wrap]`.
+//!
+//! Each call is logged to `wasi:logging` at info level in the `audit`
+//! context as one JSON object:
+//! `{"export_name", "input_hash", "output_hash", "duration_ms", "timestamp"}`.
+//! Hashes are SHA-256 over the compact JSON serialization of the argument
+//! tuple and of the return value, streamed into the hasher. Fields are
+//! written in WIT declaration order and the WIT types hold no maps, so equal
+//! values always hash alike and records can be matched against known inputs
+//! without storing them; `timestamp` is milliseconds since the Unix epoch.
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    bindings::wasi::{
+        clocks::{monotonic_clock, wall_clock},
+        logging::logging::{log, Level},
+    },
+    sha256,
+};
+
+pub(crate) fn hash<T: Serialize + ?Sized>(value: &T) -> String {
+    let mut hasher = sha256::Sha256::new();
+    serde_json::to_writer(&mut hasher, value).expect("WIT values serialize to JSON");
+    sha256::hex(&hasher.finalize())
+}
+
+/// Runs `call` and logs its audit record.
+pub(crate) fn record<T: Serialize>(
+    export_name: &str,
+    input_hash: String,
+    call: impl FnOnce() -> T,
+) -> T {
+    let timestamp = wall_clock::now();
+    let started = monotonic_clock::now();
+    let output = call();
+    let duration_ms = (monotonic_clock::now() - started) / 1_000_000;
+
+    let entry = json!({
+        "export_name": export_name,
+        "input_hash": input_hash,
+        "output_hash": hash(&output),
+        "duration_ms": duration_ms,
+        "timestamp": timestamp.seconds * 1000 + u64::from(timestamp.nanoseconds / 1_000_000),
+    });
+    log(Level::Info, "audit", &entry.to_string());
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::exports::wasmcloud::ai::response_handler::PromptOptions;
+
+    #[test]
+    fn hashes_the_json_encoding_of_the_arguments() {
+        let options = PromptOptions {
+            temperature: Some(0.5),
+            top_p: None,
+            max_tokens: Some(256),
+            model: None,
+            stop: Some(vec!["END".to_string()]),
+            max_cost_usd: None,
+        };
+        // SHA-256 of
+        // ["Hello",{"temperature":0.5,"top_p":null,"max_tokens":256,"model":null,"stop":["END"],"max_cost_usd":null}]
+        assert_eq!(
+            hash(&(&"Hello".to_string(), &options)),
+            "f919d39f09debd707158aae3cef44c9764e4a55f4b12a147f1b96ae77a3cae97"
+        );
+        // SHA-256 of {"Ok":"Hi"}
+        assert_eq!(
+            hash(&Ok::<String, String>("Hi".to_string())),
+            "f7d0e07a14e413a5d1a297b76f74bad2c3712f54b97194cf5dbafff95829daab"
+        );
+    }
+
+    #[test]
+    fn distinguishes_argument_boundaries_and_missing_values() {
+        assert_ne!(hash(&("a,b",)), hash(&("a", "b")));
+        assert_ne!(hash(&(None::<String>,)), hash(&(Some("".to_string()),)));
+    }
+}
@@ -0,0 +1,60 @@
+//! Zero-shot estimate of whether a text was written by an AI model.
+//!
+//! The result is probabilistic: the model judges stylistic markers, which
+//! human writers also produce and which edited AI text can lack. Treat it
+//! as one signal, never as proof of authorship.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorshipResult {
+    pub likely_ai: bool,
+    /// From 0 to 1.
+    pub confidence: f32,
+    pub indicators: Vec<String>,
+}
+
+const INSTRUCTIONS: &str = "You are a forensic linguist estimating whether a text was machine-generated. \
+Weigh stylistic markers of AI generation: repetitive phrasing and sentence structure, over-hedging and balanced both-sides framing, \
+unusually perfect grammar and uniform tone, generic statements, and a lack of personal anecdotes, specific details, or idiosyncrasies. \
+Also weigh markers of human writing. Give a verdict, your confidence from 0 to 1 (low when the evidence is mixed or the text is short), \
+and the specific indicators you observed, quoting the text where possible.";
+
+/// Asks the model whether `text` is likely AI-generated.
+pub async fn classify_authorship(text: String) -> Result<AuthorshipResult> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "likely_ai": { "type": "boolean" },
+            "confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+            "indicators": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["likely_ai", "confidence", "indicators"],
+        "additionalProperties": false
+    });
+
+    let result: AuthorshipResult =
+        structured::request("authorship", INSTRUCTIONS, &text, schema).await?;
+
+    ensure!(
+        (0.0..=1.0).contains(&result.confidence),
+        "confidence must be between 0 and 1, got {}",
+        result.confidence
+    );
+
+    Ok(result)
+}
+
+impl From<AuthorshipResult> for wit::AuthorshipResult {
+    fn from(result: AuthorshipResult) -> Self {
+        Self {
+            likely_ai: result.likely_ai,
+            confidence: result.confidence,
+            indicators: result.indicators,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Prompts with a spend cap.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit,
+    cost, fetch, parse_complete_response,
+    policy::{PolicyReport, PolicyStage},
+    pricing::PricingTable,
+    Endpoint, Usage, MODEL,
+};
+
+/// The API rejects `max_output_tokens` below this value, so a budget that
+/// cannot pay for this many output tokens cannot produce a useful answer.
+const MIN_OUTPUT_TOKENS: u32 = 16;
+
+#[derive(Debug)]
+pub struct BudgetedResponse {
+    pub text: String,
+    pub actual_cost_usd: f64,
+    pub truncated: bool,
+}
+
+/// Sends `prompt` with `max_output_tokens` capped so the request cannot cost
+/// more than `max_cost_usd`, and reports the cost computed from the returned
+/// usage. The call is still admitted against the request ceiling (see
+/// [`request_budget`](crate::request_budget)), which the budget does not
+/// replace.
+pub async fn prompt_with_budget(prompt: String, max_cost_usd: f32) -> Result<BudgetedResponse> {
+    let max_cost_usd = f64::from(max_cost_usd);
+    let pricing = PricingTable::OPENAI
+        .lookup(MODEL)
+        .expect("default model is priced");
+    let estimate = cost::estimate_request_cost(&prompt, None, MODEL);
+
+    let minimum_useful_request_cost = pricing.cost(estimate.input_tokens, MIN_OUTPUT_TOKENS);
+    if max_cost_usd < minimum_useful_request_cost {
+        return Err(PolicyReport {
+            policy: "request-budget",
+            rules: vec!["minimum-useful-cost".to_string()],
+            stage: PolicyStage::PreSend,
+            detail: format!(
+                "budget of ${max_cost_usd:.6} is below the minimum useful request cost of ${minimum_useful_request_cost:.6}"
+            ),
+            remediation: "Raise the budget or shorten the prompt.",
+        }
+        .reject());
+    }
+
+    let input_cost = pricing.cost(estimate.input_tokens, 0);
+    let affordable_tokens =
+        ((max_cost_usd - input_cost) * 1_000_000.0 / pricing.output_per_million).floor() as u32;
+    if estimate.estimated_cost_usd > max_cost_usd {
+        eprintln!(
+            "[COMPONENT] Estimated cost ${:.6} exceeds budget ${max_cost_usd:.6}, limiting output to {affordable_tokens} tokens",
+            estimate.estimated_cost_usd
+        );
+    }
+
+    let body = json!({
+        "model": MODEL,
+        "input": prompt,
+        "max_output_tokens": affordable_tokens.max(MIN_OUTPUT_TOKENS),
+        "stream": false,
+    });
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+
+    let json: Value = serde_json::from_str(&raw_response).context("Failed to parse JSON")?;
+    let usage = Usage::parse(Endpoint::Responses, &json).unwrap_or_default();
+    let actual_cost_usd = pricing.cost(usage.input_tokens, usage.output_tokens);
+    let truncated = json["status"] == "incomplete";
+
+    // A response cut off before any text was produced has no output message.
+    let text = match parse_complete_response(&raw_response) {
+        Ok(parsed) => parsed.text,
+        Err(_) if truncated => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    Ok(BudgetedResponse {
+        text,
+        actual_cost_usd,
+        truncated,
+    })
+}
+
+impl From<BudgetedResponse> for wit::BudgetedResponse {
+    fn from(response: BudgetedResponse) -> Self {
+        Self {
+            text: response.text,
+            actual_cost_usd: response.actual_cost_usd,
+            truncated: response.truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::request_budget;
+
+    #[test]
+    fn the_call_is_charged_its_reported_cost() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        let response = block_on(prompt_with_budget("Hello".to_string(), 0.01)).unwrap();
+        assert!(response.actual_cost_usd > 0.0);
+        let ledger = request_budget::current();
+        assert_eq!(ledger.calls, 1);
+        assert!((ledger.spent_usd - response.actual_cost_usd).abs() < 1e-12);
+    }
+
+    #[test]
+    fn the_request_ceiling_still_applies_under_a_larger_budget() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        request_budget::set_ceiling(1e-9).unwrap();
+        let error = block_on(prompt_with_budget("Hello".to_string(), 0.01)).unwrap_err();
+        let report = error.downcast_ref::<PolicyReport>().unwrap();
+        assert_eq!(report.rules, ["request-budget-exceeded"]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Discovery of what this build supports and how it is configured.
+
+use anyhow::Result;
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit, configured_model, middleware,
+    GUEST_EXPORTS, REQUEST_OPTIONS,
+};
+
+/// The WIT definition the bindings are generated from.
+const WORLD_WIT: &str = include_str!("../wit/world.wit");
+
+/// Providers compiled into the component, selectable with `AI_PROVIDER`.
+const PROVIDERS: &[&str] = &["openai", "mock"];
+
+#[derive(Debug)]
+pub struct Capabilities {
+    pub interface_version: String,
+    pub exports: Vec<String>,
+    pub providers: Vec<String>,
+    pub provider: String,
+    pub default_model: String,
+    pub middleware: Vec<String>,
+    pub chaos_enabled: bool,
+    pub request_options: Vec<String>,
+}
+
+/// Describes this build: the interface version, export list, providers and
+/// request options are fixed at compile time, the active provider,
+/// middleware and chaos switch come from the environment.
+pub fn capabilities() -> Result<Capabilities> {
+    middleware::install_from_env()?;
+
+    let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+    Ok(Capabilities {
+        interface_version: interface_version().to_string(),
+        exports: strings(GUEST_EXPORTS),
+        providers: strings(PROVIDERS),
+        provider: std::env::var("AI_PROVIDER").unwrap_or_else(|_| PROVIDERS[0].to_string()),
+        default_model: configured_model()?,
+        middleware: strings(&middleware::registered_names()),
+        chaos_enabled: std::env::var("AI_CHAOS_ENABLED").as_deref() == Ok("true"),
+        request_options: strings(REQUEST_OPTIONS),
+    })
+}
+
+/// The version of the `wasmcloud:ai` package declared in the WIT, which is
+/// what callers bind against (the crate version changes independently).
+fn interface_version() -> &'static str {
+    WORLD_WIT
+        .lines()
+        .find_map(|line| {
+            line.trim()
+                .strip_prefix("package wasmcloud:ai@")?
+                .strip_suffix(';')
+        })
+        .expect("world.wit declares a versioned wasmcloud:ai package")
+}
+
+impl From<Capabilities> for wit::CapabilityReport {
+    fn from(capabilities: Capabilities) -> Self {
+        Self {
+            interface_version: capabilities.interface_version,
+            exports: capabilities.exports,
+            providers: capabilities.providers,
+            provider: capabilities.provider,
+            default_model: capabilities.default_model,
+            middleware: capabilities.middleware,
+            chaos_enabled: capabilities.chaos_enabled,
+            request_options: capabilities.request_options,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{parse_structured_prompt, StructuredPrompt};
+
+    #[test]
+    fn reports_the_wit_package_version() {
+        let version = interface_version();
+        let parts: Vec<&str> = version.split('.').collect();
+        assert_eq!(parts.len(), 3, "{version}");
+        assert!(
+            parts.iter().all(|part| part.parse::<u32>().is_ok()),
+            "{version}"
+        );
+    }
+
+    #[test]
+    fn exports_match_the_wit_interface() {
+        let interface = &WORLD_WIT[WORLD_WIT.find("interface response-handler").unwrap()..];
+        let declared: BTreeSet<&str> = interface
+            .lines()
+            .filter_map(|line| line.trim().split_once(": func("))
+            .map(|(name, _)| name)
+            .collect();
+        let exported: BTreeSet<&str> = GUEST_EXPORTS.iter().copied().collect();
+        assert_eq!(exported, declared);
+    }
+
+    #[test]
+    fn request_options_match_the_structured_prompt() {
+        // The destructuring fails to build when StructuredPrompt gains a
+        // field that is not named here.
+        macro_rules! fields {
+            ($($field:ident),*) => {{
+                let _exhaustive = |prompt: StructuredPrompt| {
+                    let StructuredPrompt { input: _, $($field: _),* } = prompt;
+                };
+                [$(stringify!($field)),*]
+            }};
+        }
+        let fields = fields!(
+            model,
+            instructions,
+            temperature,
+            top_p,
+            max_output_tokens,
+            stop,
+            max_cost_usd,
+            tenant
+        );
+        assert_eq!(REQUEST_OPTIONS, fields);
+
+        let values = serde_json::json!({
+            "model": "gpt-4.1",
+            "instructions": "Be brief.",
+            "temperature": 0.5,
+            "top_p": 0.9,
+            "max_output_tokens": 64,
+            "stop": ["END"],
+            "max_cost_usd": 0.01,
+            "tenant": "acme",
+        });
+        for option in REQUEST_OPTIONS {
+            let prompt = serde_json::json!({ "input": "Hi", *option: values[*option] });
+            assert!(
+                parse_structured_prompt(prompt.to_string()).is_ok(),
+                "{option} is not accepted"
+            );
+        }
+    }
+}
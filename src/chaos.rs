@@ -0,0 +1,251 @@
+//! Failure injection for chaos testing, applied in [`crate::fetch`].
+//!
+//! Nothing is injected unless `AI_CHAOS_ENABLED=true`; the other settings
+//! are ignored without that master switch. Configuration:
+//!
+//! - `AI_CHAOS_RATE`: probability in `[0, 1]` that a request is faulted
+//!   (default `0`)
+//! - `AI_CHAOS_FAULTS`: comma-separated faults to choose from, any of `429`,
+//!   `500`, `timeout`, `truncate` (default all four)
+//!
+//! Injected status and timeout faults fail the request with the same
+//! [`OpenAiError`] a real failure would produce, plus an `INJECTED` marker
+//! in its body or message; `truncate` sends
+//! the real request and cuts its response body in half. Every injection is
+//! logged.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{bindings::wasi::random::random, error::OpenAiError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fault {
+    TooManyRequests,
+    ServerError,
+    Timeout,
+    TruncatedBody,
+}
+
+impl Fault {
+    const ALL: [Fault; 4] = [
+        Fault::TooManyRequests,
+        Fault::ServerError,
+        Fault::Timeout,
+        Fault::TruncatedBody,
+    ];
+
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name.trim() {
+            "429" => Self::TooManyRequests,
+            "500" => Self::ServerError,
+            "timeout" => Self::Timeout,
+            "truncate" => Self::TruncatedBody,
+            other => bail!(
+                "unknown AI_CHAOS_FAULTS entry {other:?}, expected 429, 500, timeout, or truncate"
+            ),
+        })
+    }
+}
+
+/// Decides whether to fault the current request, and with what.
+pub(crate) fn choose_fault() -> Result<Option<Fault>> {
+    let Some((rate, faults)) = config(|name| std::env::var(name).ok())? else {
+        return Ok(None);
+    };
+    let fault = decide(rate, unit_random(), &faults);
+    if let Some(fault) = fault {
+        eprintln!("[COMPONENT] INJECTED chaos fault {fault:?}");
+    }
+    Ok(fault)
+}
+
+/// Reads the chaos settings through `var`: the fault rate and the faults to
+/// choose from, or `None` when chaos is disabled.
+fn config(var: impl Fn(&str) -> Option<String>) -> Result<Option<(f64, Vec<Fault>)>> {
+    if var("AI_CHAOS_ENABLED").as_deref() != Some("true") {
+        return Ok(None);
+    }
+
+    let rate: f64 = match var("AI_CHAOS_RATE") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("AI_CHAOS_RATE must be a number, got {value:?}"))?,
+        None => 0.0,
+    };
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("AI_CHAOS_RATE must be between 0 and 1, got {rate}");
+    }
+    let faults = match var("AI_CHAOS_FAULTS") {
+        Some(value) => value
+            .split(',')
+            .map(Fault::parse)
+            .collect::<Result<Vec<_>>>()?,
+        None => Fault::ALL.to_vec(),
+    };
+    Ok(Some((rate, faults)))
+}
+
+/// Picks the fault for a request given `roll`, uniform in `[0, 1)`: none
+/// unless `roll < rate`, in which case `roll / rate` (again uniform) selects
+/// from `faults`.
+fn decide(rate: f64, roll: f64, faults: &[Fault]) -> Option<Fault> {
+    if faults.is_empty() || roll >= rate {
+        return None;
+    }
+    let index = (roll / rate * faults.len() as f64) as usize;
+    Some(faults[index.min(faults.len() - 1)])
+}
+
+/// Fails with the [`OpenAiError`] a real occurrence of `fault` would
+/// produce, marked `INJECTED`. Returns `Ok` for faults that apply to the
+/// response body instead.
+pub(crate) fn fail_before_send(fault: Fault) -> Result<()> {
+    let error = match fault {
+        // A 429 has no body to carry the marker, so it goes in the message.
+        Fault::TooManyRequests => {
+            return Err(anyhow::Error::new(OpenAiError::RateLimited {
+                retry_after_secs: None,
+            })
+            .context("HTTP 429 from OpenAI (INJECTED)"))
+        }
+        Fault::ServerError => OpenAiError::HttpError {
+            status: 500,
+            body: "INJECTED".to_string(),
+        },
+        Fault::Timeout => {
+            OpenAiError::IoError("request to OpenAI timed out (INJECTED)".to_string())
+        }
+        Fault::TruncatedBody => return Ok(()),
+    };
+    Err(error.into())
+}
+
+/// Applies body faults to a received response.
+pub(crate) fn corrupt_body(fault: Fault, mut body: Vec<u8>) -> Vec<u8> {
+    if fault == Fault::TruncatedBody {
+        body.truncate(body.len() / 2);
+    }
+    body
+}
+
+/// A uniform value in `[0, 1)`, from the top 53 bits of a random `u64`.
+fn unit_random() -> f64 {
+    (random::get_random_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, explain::ErrorCode};
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn faults_only_when_the_roll_is_below_the_rate() {
+        let all = Fault::ALL;
+        assert_eq!(decide(0.0, 0.0, &all), None);
+        assert_eq!(decide(0.5, 0.5, &all), None);
+        assert_eq!(decide(0.5, 0.99, &all), None);
+        assert!(decide(0.5, 0.49, &all).is_some());
+        assert!(decide(1.0, 0.999_999, &all).is_some());
+        assert_eq!(decide(1.0, 0.2, &[]), None);
+    }
+
+    #[test]
+    fn spreads_faulted_rolls_evenly_over_the_scope() {
+        let all = Fault::ALL;
+        assert_eq!(decide(0.4, 0.0, &all), Some(Fault::TooManyRequests));
+        assert_eq!(decide(0.4, 0.15, &all), Some(Fault::ServerError));
+        assert_eq!(decide(0.4, 0.25, &all), Some(Fault::Timeout));
+        assert_eq!(decide(0.4, 0.399_999, &all), Some(Fault::TruncatedBody));
+
+        let only_timeouts = [Fault::Timeout];
+        assert_eq!(decide(1.0, 0.7, &only_timeouts), Some(Fault::Timeout));
+    }
+
+    #[test]
+    fn reads_the_config_behind_the_master_switch() {
+        assert_eq!(config(vars(&[("AI_CHAOS_RATE", "1")])).unwrap(), None);
+        assert_eq!(
+            config(vars(&[("AI_CHAOS_ENABLED", "true")])).unwrap(),
+            Some((0.0, Fault::ALL.to_vec()))
+        );
+        assert_eq!(
+            config(vars(&[
+                ("AI_CHAOS_ENABLED", "true"),
+                ("AI_CHAOS_RATE", "0.25"),
+                ("AI_CHAOS_FAULTS", "429, timeout"),
+            ]))
+            .unwrap(),
+            Some((0.25, vec![Fault::TooManyRequests, Fault::Timeout]))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        for (name, value) in [
+            ("AI_CHAOS_RATE", "often"),
+            ("AI_CHAOS_RATE", "1.5"),
+            ("AI_CHAOS_FAULTS", "429,503"),
+        ] {
+            let lookup = vars(&[("AI_CHAOS_ENABLED", "true"), (name, value)]);
+            assert!(config(lookup).is_err(), "{name}={value}");
+        }
+    }
+
+    #[test]
+    fn injected_failures_are_typed_and_marked() {
+        let injected = |fault| fail_before_send(fault).unwrap_err();
+
+        let error = injected(Fault::TooManyRequests);
+        assert_eq!(
+            error.downcast_ref::<OpenAiError>(),
+            Some(&OpenAiError::RateLimited {
+                retry_after_secs: None
+            })
+        );
+        assert_eq!(error.to_string(), "HTTP 429 from OpenAI (INJECTED)");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::RateLimited);
+        assert!(matches!(
+            wit::OpenaiError::from(&error),
+            wit::OpenaiError::RateLimited(None)
+        ));
+
+        let error = injected(Fault::ServerError);
+        assert_eq!(
+            error.downcast_ref::<OpenAiError>(),
+            Some(&OpenAiError::HttpError {
+                status: 500,
+                body: "INJECTED".to_string()
+            })
+        );
+        assert_eq!(error.to_string(), "HTTP 500 from OpenAI: INJECTED");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::HttpError);
+
+        let error = injected(Fault::Timeout);
+        assert!(matches!(
+            error.downcast_ref::<OpenAiError>(),
+            Some(OpenAiError::IoError(message)) if message.ends_with("(INJECTED)")
+        ));
+        assert_eq!(ErrorCode::of(&error), ErrorCode::IoError);
+
+        assert!(fail_before_send(Fault::TruncatedBody).is_ok());
+    }
+
+    #[test]
+    fn truncation_halves_the_body() {
+        assert_eq!(
+            corrupt_body(Fault::TruncatedBody, b"abcdef".to_vec()),
+            b"abc"
+        );
+        assert_eq!(corrupt_body(Fault::Timeout, b"abcdef".to_vec()), b"abcdef");
+    }
+}
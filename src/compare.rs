@@ -0,0 +1,85 @@
+//! Side-by-side answers from several models to the same prompt.
+
+use anyhow::{anyhow, ensure, Context, Result};
+use futures::future::try_join_all;
+use serde_json::json;
+
+use crate::{
+    bindings::{exports::wasmcloud::ai::response_handler as wit, wasi::clocks::monotonic_clock},
+    cost, fetch, parse_complete_response,
+    pricing::PricingTable,
+    Endpoint,
+};
+
+/// Upper bound on models compared per call.
+const MAX_MODELS: usize = 10;
+
+#[derive(Debug)]
+pub struct ModelResponse {
+    pub model: String,
+    pub response: String,
+    pub latency_ms: u32,
+    pub tokens_used: u32,
+    pub estimated_cost: f64,
+}
+
+/// Sends `prompt` to every model in `models` concurrently and returns their
+/// answers, cheapest first.
+///
+/// `estimated_cost` is priced from the returned usage; models missing from
+/// the pricing table fall back to the estimate for the default model.
+pub async fn compare_models(prompt: String, models: Vec<String>) -> Result<Vec<ModelResponse>> {
+    ensure!(!models.is_empty(), "no models to compare");
+    ensure!(
+        models.len() <= MAX_MODELS,
+        "at most {MAX_MODELS} models can be compared, got {}",
+        models.len()
+    );
+
+    let prompt = &prompt;
+    let requests = models.into_iter().map(|model| async move {
+        let body = json!({
+            "model": model,
+            "input": prompt,
+            "stream": false,
+        });
+        let started = monotonic_clock::now();
+        let raw_response =
+            String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+                .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+        let latency_ms = (monotonic_clock::now() - started) / 1_000_000;
+
+        let parsed = parse_complete_response(&raw_response)
+            .with_context(|| format!("model {model} returned no answer"))?;
+        let usage = parsed.usage.unwrap_or_default();
+        let (input_tokens, output_tokens) = (usage.input_tokens, usage.output_tokens);
+        let estimated_cost = match PricingTable::OPENAI.lookup(&model) {
+            Some(pricing) => pricing.cost(input_tokens, output_tokens),
+            None => cost::estimate_request_cost(prompt, None, &model).estimated_cost_usd,
+        };
+
+        Ok::<_, anyhow::Error>(ModelResponse {
+            model,
+            response: parsed.text,
+            latency_ms: u32::try_from(latency_ms).unwrap_or(u32::MAX),
+            tokens_used: input_tokens.saturating_add(output_tokens),
+            estimated_cost,
+        })
+    });
+
+    let mut responses = try_join_all(requests).await?;
+    responses.sort_by(|a, b| a.estimated_cost.total_cmp(&b.estimated_cost));
+    Ok(responses)
+}
+
+impl From<ModelResponse> for wit::ModelResponse {
+    fn from(response: ModelResponse) -> Self {
+        Self {
+            model: response.model,
+            response: response.response,
+            latency_ms: response.latency_ms,
+            tokens_used: response.tokens_used,
+            estimated_cost: response.estimated_cost,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Handling of answers the provider flagged with its content filter.
+//!
+//! A response counts as filtered when `incomplete_details.reason` is
+//! `content_filter` (OpenAI) or any `content_filter_results` object in it
+//! has a category with `filtered: true` (Azure OpenAI). What happens then is
+//! set per tenant, the `tenant` of a structured prompt:
+//!
+//! - `suppress`: the answer is dropped and the request fails with a
+//!   [`PolicyReport`] naming the filter categories
+//! - `flag` (default): the answer is returned unchanged, together with a
+//!   non-blocking [`PolicyReport`] for `prompt-handle-result` callers
+//! - `passthrough`: the answer is returned unchanged
+//!
+//! The policy of a tenant is its entry in `OPENAI_CONTENT_FILTER_TENANT_POLICIES`
+//! (a JSON object from tenant to policy), else `OPENAI_CONTENT_FILTER_POLICY`,
+//! else `flag`.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+use crate::policy::{PolicyReport, PolicyStage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterPolicy {
+    Suppress,
+    Flag,
+    Passthrough,
+}
+
+impl FilterPolicy {
+    fn parse(name: &str, value: &str) -> Result<Self> {
+        Ok(match value {
+            "flag" => Self::Flag,
+            "suppress" => Self::Suppress,
+            "passthrough" => Self::Passthrough,
+            other => bail!("{name} must be suppress, flag, or passthrough, got {other:?}"),
+        })
+    }
+}
+
+/// The policy for `tenant`, read through `var`. Both variables are
+/// validated on every request, whichever one applies.
+fn resolve_policy(
+    tenant: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<FilterPolicy> {
+    const TENANTS: &str = "OPENAI_CONTENT_FILTER_TENANT_POLICIES";
+    let default = match var("OPENAI_CONTENT_FILTER_POLICY") {
+        Some(value) => FilterPolicy::parse("OPENAI_CONTENT_FILTER_POLICY", &value)?,
+        None => FilterPolicy::Flag,
+    };
+    let tenants: HashMap<String, String> = match var(TENANTS) {
+        Some(value) => serde_json::from_str(&value)
+            .map_err(|e| anyhow!("{TENANTS} must be a JSON object from tenant to policy: {e}"))?,
+        None => HashMap::new(),
+    };
+    let mut policies = HashMap::new();
+    for (name, value) in &tenants {
+        policies.insert(
+            name.as_str(),
+            FilterPolicy::parse(&format!("{TENANTS} entry {name:?}"), value)?,
+        );
+    }
+    Ok(tenant
+        .and_then(|tenant| policies.get(tenant).copied())
+        .unwrap_or(default))
+}
+
+/// Applies the policy of `tenant` to the answer of `raw_response`. Returns
+/// the report of a flagged answer, or fails with the report of a
+/// suppressed one.
+pub(crate) fn apply(raw_response: &str, tenant: Option<&str>) -> Result<Option<PolicyReport>> {
+    apply_with(raw_response, tenant, |name| std::env::var(name).ok())
+}
+
+fn apply_with(
+    raw_response: &str,
+    tenant: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Option<PolicyReport>> {
+    let policy = resolve_policy(tenant, var)?;
+    let Ok(response) = serde_json::from_str::<Value>(raw_response) else {
+        return Ok(None);
+    };
+    let categories = filtered_categories(&response);
+    if categories.is_empty() {
+        return Ok(None);
+    }
+
+    match policy {
+        FilterPolicy::Passthrough => Ok(None),
+        FilterPolicy::Flag => {
+            eprintln!(
+                "[COMPONENT] Content filter flagged the answer: {}",
+                categories.join(", ")
+            );
+            Ok(Some(flagged(categories)))
+        }
+        FilterPolicy::Suppress => Err(suppressed(categories).reject()),
+    }
+}
+
+fn flagged(categories: Vec<String>) -> PolicyReport {
+    PolicyReport {
+        policy: "content-filter",
+        detail: format!(
+            "the provider's content filter flagged the answer ({})",
+            categories.join(", ")
+        ),
+        rules: categories,
+        stage: PolicyStage::PostReceive,
+        remediation: "Review the answer before showing it, or set the tenant's content filter policy to suppress (see OPENAI_CONTENT_FILTER_TENANT_POLICIES) to drop flagged answers.",
+    }
+}
+
+pub(crate) fn suppressed(categories: Vec<String>) -> PolicyReport {
+    PolicyReport {
+        policy: "content-filter",
+        detail: format!(
+            "the provider's content filter flagged the answer ({})",
+            categories.join(", ")
+        ),
+        rules: categories,
+        stage: PolicyStage::PostReceive,
+        remediation: "Rephrase the prompt, or set the tenant's content filter policy to flag (see OPENAI_CONTENT_FILTER_TENANT_POLICIES) to receive flagged answers.",
+    }
+}
+
+/// Names of the filter categories that fired, deduplicated in order of
+/// appearance. An OpenAI content-filter stop without categories is reported
+/// as `content_filter`.
+pub(crate) fn filtered_categories(response: &Value) -> Vec<String> {
+    let mut categories = Vec::new();
+    collect_azure_categories(response, &mut categories);
+    if categories.is_empty() && response["incomplete_details"]["reason"] == "content_filter" {
+        categories.push("content_filter".to_string());
+    }
+    categories
+}
+
+fn collect_azure_categories(value: &Value, categories: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                if key == "content_filter_results" {
+                    for (category, result) in child.as_object().into_iter().flatten() {
+                        if result["filtered"] == true && !categories.contains(category) {
+                            categories.push(category.clone());
+                        }
+                    }
+                } else {
+                    collect_azure_categories(child, categories);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_azure_categories(item, categories);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A Responses API answer cut off by OpenAI's content filter.
+    const OPENAI_FILTERED: &str = r#"{
+        "id": "resp_1",
+        "object": "response",
+        "status": "incomplete",
+        "incomplete_details": { "reason": "content_filter" },
+        "output": [{
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": "Here is how" }]
+        }],
+        "usage": { "input_tokens": 12, "output_tokens": 3, "total_tokens": 15 }
+    }"#;
+
+    /// An Azure OpenAI chat completion with filter annotations on both the
+    /// prompt and the answer.
+    const AZURE_FILTERED: &str = r#"{
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "prompt_filter_results": [{
+            "prompt_index": 0,
+            "content_filter_results": {
+                "hate": { "filtered": false, "severity": "safe" },
+                "self_harm": { "filtered": false, "severity": "safe" }
+            }
+        }],
+        "choices": [{
+            "index": 0,
+            "finish_reason": "content_filter",
+            "message": { "role": "assistant", "content": "" },
+            "content_filter_results": {
+                "hate": { "filtered": false, "severity": "safe" },
+                "violence": { "filtered": true, "severity": "medium" },
+                "sexual": { "filtered": true, "severity": "high" }
+            }
+        }]
+    }"#;
+
+    /// An Azure OpenAI chat completion whose annotations all passed.
+    const AZURE_CLEAN: &str = r#"{
+        "choices": [{
+            "message": { "role": "assistant", "content": "Hello!" },
+            "content_filter_results": {
+                "violence": { "filtered": false, "severity": "safe" }
+            }
+        }]
+    }"#;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn policy(value: &str) -> impl Fn(&str) -> Option<String> {
+        vars(&[("OPENAI_CONTENT_FILTER_POLICY", value)])
+    }
+
+    #[test]
+    fn finds_the_categories_of_both_response_shapes() {
+        let categories = |raw: &str| filtered_categories(&serde_json::from_str(raw).unwrap());
+        assert_eq!(categories(OPENAI_FILTERED), ["content_filter"]);
+        assert_eq!(categories(AZURE_FILTERED), ["sexual", "violence"]);
+        assert!(categories(AZURE_CLEAN).is_empty());
+    }
+
+    #[test]
+    fn flag_mode_reports_without_blocking() {
+        for (raw, rules) in [
+            (OPENAI_FILTERED, vec!["content_filter"]),
+            (AZURE_FILTERED, vec!["sexual", "violence"]),
+        ] {
+            let report = apply_with(raw, None, vars(&[])).unwrap().expect("flagged");
+            assert_eq!(report.policy, "content-filter");
+            assert_eq!(report.rules, rules);
+            assert_eq!(report.stage, PolicyStage::PostReceive);
+
+            let explicit = apply_with(raw, None, policy("flag")).unwrap().unwrap();
+            assert_eq!(explicit.rules, rules);
+        }
+    }
+
+    #[test]
+    fn suppress_mode_fails_with_the_report() {
+        for raw in [OPENAI_FILTERED, AZURE_FILTERED] {
+            let error = apply_with(raw, None, policy("suppress")).unwrap_err();
+            let report = error.downcast::<PolicyReport>().unwrap();
+            assert_eq!(report.policy, "content-filter");
+            assert!(!report.rules.is_empty());
+        }
+    }
+
+    #[test]
+    fn passthrough_mode_reports_nothing() {
+        for raw in [OPENAI_FILTERED, AZURE_FILTERED] {
+            assert!(apply_with(raw, None, policy("passthrough"))
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn unfiltered_answers_pass_every_mode() {
+        for mode in ["flag", "suppress", "passthrough"] {
+            for raw in [AZURE_CLEAN, "not json"] {
+                assert!(
+                    apply_with(raw, None, policy(mode)).unwrap().is_none(),
+                    "{mode}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_the_policy_per_tenant() {
+        let var = vars(&[
+            ("OPENAI_CONTENT_FILTER_POLICY", "passthrough"),
+            (
+                "OPENAI_CONTENT_FILTER_TENANT_POLICIES",
+                r#"{"strict": "suppress", "lenient": "flag"}"#,
+            ),
+        ]);
+        assert_eq!(
+            resolve_policy(Some("strict"), &var).unwrap(),
+            FilterPolicy::Suppress
+        );
+        assert_eq!(
+            resolve_policy(Some("lenient"), &var).unwrap(),
+            FilterPolicy::Flag
+        );
+        assert_eq!(
+            resolve_policy(Some("other"), &var).unwrap(),
+            FilterPolicy::Passthrough
+        );
+        assert_eq!(
+            resolve_policy(None, &var).unwrap(),
+            FilterPolicy::Passthrough
+        );
+        assert_eq!(
+            resolve_policy(Some("strict"), vars(&[])).unwrap(),
+            FilterPolicy::Flag
+        );
+
+        assert!(apply_with(AZURE_FILTERED, Some("strict"), &var).is_err());
+        assert!(apply_with(AZURE_FILTERED, Some("other"), &var)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_policies_even_when_unused() {
+        for (name, value) in [
+            ("OPENAI_CONTENT_FILTER_POLICY", "block"),
+            (
+                "OPENAI_CONTENT_FILTER_TENANT_POLICIES",
+                r#"{"acme": "block"}"#,
+            ),
+            ("OPENAI_CONTENT_FILTER_TENANT_POLICIES", r#"["suppress"]"#),
+        ] {
+            let error = resolve_policy(None, vars(&[(name, value)])).unwrap_err();
+            assert!(error.to_string().contains(name), "{error}");
+            assert!(apply_with(AZURE_CLEAN, None, vars(&[(name, value)])).is_err());
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Cost estimation for a request before it is sent.
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit, pricing::PricingTable,
+    tokens::count_tokens, MODEL,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CostEstimate {
+    pub input_tokens: u32,
+    pub estimated_output_tokens: u32,
+    pub estimated_cost_usd: f64,
+}
+
+/// Estimates the tokens and cost of sending `prompt` (and optional `system`
+/// instructions) to `model`. Models missing from the pricing table are
+/// priced as the component's default model.
+pub fn estimate_request_cost(prompt: &str, system: Option<&str>, model: &str) -> CostEstimate {
+    let table = PricingTable::OPENAI;
+    let pricing = table
+        .lookup(model)
+        .or_else(|| table.lookup(MODEL))
+        .expect("default model is priced");
+
+    let input_tokens = count_tokens(prompt).saturating_add(system.map_or(0, count_tokens));
+    let estimated_output_tokens = (f64::from(input_tokens) * pricing.output_ratio).ceil() as u32;
+
+    CostEstimate {
+        input_tokens,
+        estimated_output_tokens,
+        estimated_cost_usd: pricing.cost(input_tokens, estimated_output_tokens),
+    }
+}
+
+impl From<CostEstimate> for wit::CostEstimate {
+    fn from(estimate: CostEstimate) -> Self {
+        Self {
+            input_tokens: estimate.input_tokens,
+            estimated_output_tokens: estimate.estimated_output_tokens,
+            estimated_cost_usd: estimate.estimated_cost_usd,
+        }
+    }
+}
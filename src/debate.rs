@@ -0,0 +1,70 @@
+//! Judging which of two arguments wins a debate.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct DebateJudgment {
+    /// `"A"`, `"B"`, or `"tie"`.
+    pub winner: String,
+    pub score_a: f32,
+    pub score_b: f32,
+    pub reasoning: String,
+}
+
+const INSTRUCTIONS: &str = "You are an impartial debate judge. \
+Evaluate each argument on the topic independently on three criteria: logic (validity and coherence of reasoning), \
+evidence (quality and relevance of support), and persuasiveness (how convincing it is to a neutral reader). \
+Do not let argument order or length sway you. Score each argument from 0 to 10, \
+declare winner A, B, or tie, and explain the decision criterion by criterion.";
+
+/// Asks the model to judge `argument_a` against `argument_b` on `topic`.
+pub async fn judge_debate(
+    topic: String,
+    argument_a: String,
+    argument_b: String,
+) -> Result<DebateJudgment> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "winner": { "type": "string", "enum": ["A", "B", "tie"] },
+            "score_a": { "type": "number", "minimum": 0, "maximum": 10 },
+            "score_b": { "type": "number", "minimum": 0, "maximum": 10 },
+            "reasoning": { "type": "string" }
+        },
+        "required": ["winner", "score_a", "score_b", "reasoning"],
+        "additionalProperties": false
+    });
+
+    let input = format!("Topic: {topic}\n\nArgument A:\n{argument_a}\n\nArgument B:\n{argument_b}");
+    let judgment: DebateJudgment =
+        structured::request("debate_judgment", INSTRUCTIONS, &input, schema).await?;
+
+    for (name, score) in [("score_a", judgment.score_a), ("score_b", judgment.score_b)] {
+        ensure!(
+            (0.0..=10.0).contains(&score),
+            "{name} must be between 0 and 10, got {score}"
+        );
+    }
+    ensure!(
+        matches!(judgment.winner.as_str(), "A" | "B" | "tie"),
+        "winner must be A, B, or tie, got {:?}",
+        judgment.winner
+    );
+
+    Ok(judgment)
+}
+
+impl From<DebateJudgment> for wit::DebateJudgment {
+    fn from(judgment: DebateJudgment) -> Self {
+        Self {
+            winner: judgment.winner,
+            score_a: judgment.score_a,
+            score_b: judgment.score_b,
+            reasoning: judgment.reasoning,
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Document chunking and embedding for RAG indexing, and vector similarity.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, fetch, Endpoint};
+
+/// Model used for embedding requests that do not name one.
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Most chunks `chunk_and_embed` embeds for one document; smaller
+/// `chunk_size` values on long documents are rejected instead of fanning
+/// out without bound.
+const MAX_CHUNKS: usize = 2048;
+
+/// Most inputs sent in one embeddings request, through the API's array
+/// `input`. [`MAX_CHUNKS`] allows at most eight concurrent requests.
+const MAX_BATCH_INPUTS: usize = 256;
+
+/// Known embedding models: native vector length, and whether the model
+/// accepts a smaller `dimensions`.
+const MODELS: &[(&str, u32, bool)] = &[
+    ("text-embedding-3-small", 1536, true),
+    ("text-embedding-3-large", 3072, true),
+    ("text-embedding-ada-002", 1536, false),
+];
+
+#[derive(Debug, Default)]
+pub struct EmbeddingOptions {
+    /// Defaults to [`EMBEDDING_MODEL`].
+    pub model: Option<String>,
+    /// Vector length to request; only the `text-embedding-3` models accept one.
+    pub dimensions: Option<u32>,
+    /// Scale the vector to unit length before returning it.
+    pub normalize: bool,
+}
+
+#[derive(Debug)]
+pub struct EmbeddedChunk {
+    pub text: String,
+    pub start_char: u32,
+    pub end_char: u32,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    /// Position of the input this embedding is for.
+    index: Option<usize>,
+    embedding: Vec<f32>,
+}
+
+/// Splits `document` into chunks of at most `chunk_size` characters, each
+/// sharing up to `overlap` characters with the previous one, and embeds the
+/// chunks in concurrent batches of up to [`MAX_BATCH_INPUTS`]. Documents that
+/// split into more than [`MAX_CHUNKS`] chunks are rejected.
+pub async fn chunk_and_embed(
+    document: String,
+    chunk_size: u32,
+    overlap: u32,
+) -> Result<Vec<EmbeddedChunk>> {
+    ensure!(chunk_size > 0, "chunk_size must be positive");
+    ensure!(
+        overlap < chunk_size,
+        "overlap ({overlap}) must be smaller than chunk_size ({chunk_size})"
+    );
+
+    let spans = chunk(&document, chunk_size as usize, overlap as usize);
+    ensure!(
+        spans.len() <= MAX_CHUNKS,
+        "document splits into {} chunks, more than the limit of {MAX_CHUNKS}; use a larger chunk_size",
+        spans.len()
+    );
+    eprintln!(
+        "[COMPONENT] Embedding {} chunks in {} requests",
+        spans.len(),
+        spans.len().div_ceil(MAX_BATCH_INPUTS)
+    );
+
+    let options = EmbeddingOptions::default();
+    let batches = spans.chunks(MAX_BATCH_INPUTS).map(|batch| {
+        let inputs: Vec<&str> = batch.iter().map(|span| span.text.as_str()).collect();
+        let options = &options;
+        async move { embed_batch(&inputs, options).await }
+    });
+    let embeddings = try_join_all(batches).await?.into_iter().flatten();
+
+    Ok(spans
+        .into_iter()
+        .zip(embeddings)
+        .map(|(span, embedding)| EmbeddedChunk {
+            text: span.text,
+            start_char: span.start,
+            end_char: span.end,
+            embedding,
+        })
+        .collect())
+}
+
+/// Embeds a single input as `options` describe. `dimensions` is checked
+/// against [`MODELS`]; models missing from the table (for example on an
+/// OpenAI-compatible server) are passed through unchecked.
+pub async fn embed_with_options(input: &str, options: &EmbeddingOptions) -> Result<Vec<f32>> {
+    let mut embeddings = embed_batch(&[input], options).await?;
+    Ok(embeddings.remove(0))
+}
+
+/// Embeds `inputs` in one request and returns their embeddings in order.
+async fn embed_batch(inputs: &[&str], options: &EmbeddingOptions) -> Result<Vec<Vec<f32>>> {
+    ensure!(
+        inputs.iter().all(|input| !input.trim().is_empty()),
+        "input must not be empty"
+    );
+    let model = options.model.as_deref().unwrap_or(EMBEDDING_MODEL);
+
+    if let Some(dimensions) = options.dimensions {
+        ensure!(dimensions > 0, "dimensions must be positive");
+        match MODELS.iter().find(|(name, ..)| *name == model) {
+            Some((_, _, false)) => bail!("{model} does not support custom dimensions"),
+            Some((_, native, true)) => ensure!(
+                dimensions <= *native,
+                "{model} supports at most {native} dimensions, got {dimensions}"
+            ),
+            None => {
+                eprintln!("[COMPONENT] Unknown embedding model {model}, not checking dimensions")
+            }
+        }
+    }
+
+    // A single input is sent as a string, as before batching.
+    let mut body = match inputs {
+        [input] => json!({ "model": model, "input": input }),
+        _ => json!({ "model": model, "input": inputs }),
+    };
+    if let Some(dimensions) = options.dimensions {
+        body["dimensions"] = json!(dimensions);
+    }
+
+    let raw_response = fetch(Endpoint::Embeddings, serde_json::to_vec(&body)?).await?;
+    let mut embeddings = parse_embeddings(&raw_response, inputs.len())?;
+
+    for embedding in &mut embeddings {
+        if let Some(dimensions) = options.dimensions {
+            ensure!(
+                embedding.len() == dimensions as usize,
+                "requested {dimensions} dimensions but got {}",
+                embedding.len()
+            );
+        }
+        if options.normalize {
+            normalize(embedding);
+        }
+    }
+    Ok(embeddings)
+}
+
+/// Reads `expected` embeddings from an embeddings response, ordered by
+/// their `index` (or by position when the server sends none).
+fn parse_embeddings(raw_response: &[u8], expected: usize) -> Result<Vec<Vec<f32>>> {
+    let response: EmbeddingResponse =
+        serde_json::from_slice(raw_response).context("Failed to parse embeddings response")?;
+    if response.data.is_empty() {
+        return Err(anyhow!("No embedding found in response"));
+    }
+    ensure!(
+        response.data.len() == expected,
+        "expected {expected} embeddings but got {}",
+        response.data.len()
+    );
+
+    let mut slots = vec![None; expected];
+    for (position, data) in response.data.into_iter().enumerate() {
+        let index = data.index.unwrap_or(position);
+        match slots.get_mut(index) {
+            Some(slot @ None) => *slot = Some(data.embedding),
+            Some(Some(_)) => bail!("duplicate embedding for input {index}"),
+            None => bail!("embedding index {index} is out of range for {expected} inputs"),
+        }
+    }
+    Ok(slots.into_iter().flatten().collect())
+}
+
+/// Scales `vector` to unit L2 length. A zero vector has no direction and is
+/// left unchanged.
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector
+        .iter()
+        .map(|&x| f64::from(x) * f64::from(x))
+        .sum::<f64>()
+        .sqrt();
+    if norm > 0.0 {
+        for x in vector {
+            *x = (f64::from(*x) / norm) as f32;
+        }
+    }
+}
+
+/// Cosine similarity of `a` and `b`, in `[-1, 1]`. Fails for vectors of
+/// different lengths, empty vectors, and zero vectors, where it is undefined.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Result<f32> {
+    ensure!(
+        a.len() == b.len(),
+        "vectors have different lengths ({} and {})",
+        a.len(),
+        b.len()
+    );
+    ensure!(!a.is_empty(), "vectors must not be empty");
+
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+    for (&x, &y) in a.iter().zip(b) {
+        let (x, y) = (f64::from(x), f64::from(y));
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    ensure!(
+        dot.is_finite() && norm_a.is_finite() && norm_b.is_finite(),
+        "vectors must contain only finite values"
+    );
+    ensure!(
+        norm_a > 0.0 && norm_b > 0.0,
+        "cosine similarity is undefined for a zero vector"
+    );
+
+    Ok((dot / (norm_a.sqrt() * norm_b.sqrt())).clamp(-1.0, 1.0) as f32)
+}
+
+struct Span {
+    text: String,
+    start: u32,
+    end: u32,
+}
+
+/// Splits `text` into character spans. Each chunk ends at the last sentence
+/// boundary that fits, falling back to the last whitespace and then to a hard
+/// cut; the next chunk starts `overlap` characters back, moved forward to a
+/// word start. Offsets are in characters, end-exclusive, and point at the
+/// chunk text with surrounding whitespace trimmed.
+fn chunk(text: &str, chunk_size: usize, overlap: usize) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let hard_end = (start + chunk_size).min(chars.len());
+        let end = if hard_end == chars.len() {
+            hard_end
+        } else {
+            let window = start + 1..=hard_end;
+            let sentence_end = window.clone().rev().find(|&i| {
+                matches!(chars[i - 1], '.' | '!' | '?' | '。' | '！' | '？')
+                    && chars.get(i).is_none_or(|c| c.is_whitespace())
+            });
+            let word_end = || window.clone().rev().find(|&i| chars[i].is_whitespace());
+            sentence_end.or_else(word_end).unwrap_or(hard_end)
+        };
+
+        let trimmed_start = (start..end)
+            .find(|&i| !chars[i].is_whitespace())
+            .unwrap_or(end);
+        let trimmed_end = (trimmed_start..end)
+            .rev()
+            .find(|&i| !chars[i].is_whitespace())
+            .map_or(trimmed_start, |i| i + 1);
+        if trimmed_start < trimmed_end {
+            spans.push(Span {
+                text: chars[trimmed_start..trimmed_end].iter().collect(),
+                start: trimmed_start as u32,
+                end: trimmed_end as u32,
+            });
+        }
+
+        if end == chars.len() {
+            break;
+        }
+
+        let mut next = end.saturating_sub(overlap).max(start + 1);
+        // Don't start the overlap in the middle of a word.
+        if next < end && next > 0 && !chars[next - 1].is_whitespace() {
+            next = (next..end)
+                .find(|&i| chars[i].is_whitespace())
+                .unwrap_or(end);
+        }
+        start = next;
+    }
+
+    spans
+}
+
+impl From<EmbeddedChunk> for wit::EmbeddedChunk {
+    fn from(chunk: EmbeddedChunk) -> Self {
+        Self {
+            text: chunk.text,
+            start_char: chunk.start_char,
+            end_char: chunk.end_char,
+            embedding: chunk.embedding,
+        }
+    }
+}
+
+impl From<wit::EmbeddingOptions> for EmbeddingOptions {
+    fn from(options: wit::EmbeddingOptions) -> Self {
+        Self {
+            model: options.model,
+            dimensions: options.dimensions,
+            normalize: options.normalize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_documents_with_too_many_chunks_before_any_request() {
+        let document = "word ".repeat(MAX_CHUNKS + 1);
+        let error = futures::executor::block_on(chunk_and_embed(document, 1, 0))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("more than the limit of 2048"), "{error}");
+    }
+
+    #[test]
+    fn embeddings_are_ordered_by_index() {
+        let response = br#"{"data": [
+            {"index": 2, "embedding": [2.0]},
+            {"index": 0, "embedding": [0.0]},
+            {"index": 1, "embedding": [1.0]}
+        ]}"#;
+        assert_eq!(
+            parse_embeddings(response, 3).unwrap(),
+            [vec![0.0], vec![1.0], vec![2.0]]
+        );
+        let positional = br#"{"data": [{"embedding": [5.0]}, {"embedding": [6.0]}]}"#;
+        assert_eq!(
+            parse_embeddings(positional, 2).unwrap(),
+            [vec![5.0], vec![6.0]]
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_embedding_responses() {
+        for (response, expected) in [
+            (&br#"{"data": []}"#[..], "No embedding found"),
+            (br#"{"data": [{"index": 0, "embedding": [1.0]}]}"#, "expected 2 embeddings but got 1"),
+            (
+                br#"{"data": [{"index": 0, "embedding": [1.0]}, {"index": 0, "embedding": [2.0]}]}"#,
+                "duplicate embedding for input 0",
+            ),
+            (
+                br#"{"data": [{"index": 0, "embedding": [1.0]}, {"index": 5, "embedding": [2.0]}]}"#,
+                "index 5 is out of range",
+            ),
+        ] {
+            let error = parse_embeddings(response, 2).unwrap_err().to_string();
+            assert!(error.contains(expected), "{error}");
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn cosine_similarity_of_known_angles() {
+        let cos = |a: &[f32], b: &[f32]| cosine_similarity(a, b).unwrap();
+        assert_close(cos(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]), 1.0);
+        assert_close(cos(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_close(cos(&[1.0, -2.0], &[-1.0, 2.0]), -1.0);
+        assert_close(
+            cos(&[1.0, 0.0], &[1.0, 1.0]),
+            std::f32::consts::FRAC_1_SQRT_2,
+        );
+        assert_close(cos(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), 0.974_631_8);
+        // Length does not matter, only direction.
+        assert_close(cos(&[3.0, 4.0], &[300.0, 400.0]), 1.0);
+        assert_close(cos(&[1e-20, 1e-20], &[1e20, 1e20]), 1.0);
+    }
+
+    #[test]
+    fn cosine_similarity_stays_in_range() {
+        let a = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7];
+        let similarity = cosine_similarity(&a, &a).unwrap();
+        assert!((-1.0..=1.0).contains(&similarity), "{similarity}");
+        let negated: Vec<f32> = a.iter().map(|x| -x).collect();
+        let similarity = cosine_similarity(&a, &negated).unwrap();
+        assert!((-1.0..=1.0).contains(&similarity), "{similarity}");
+    }
+
+    #[test]
+    fn cosine_similarity_rejects_undefined_inputs() {
+        for (a, b, expected) in [
+            (
+                &[1.0f32, 2.0][..],
+                &[1.0f32][..],
+                "different lengths (2 and 1)",
+            ),
+            (&[], &[1.0], "different lengths (0 and 1)"),
+            (&[], &[], "must not be empty"),
+            (&[0.0, 0.0], &[1.0, 2.0], "undefined for a zero vector"),
+            (&[1.0, 2.0], &[0.0, 0.0], "undefined for a zero vector"),
+            (&[0.0], &[0.0], "undefined for a zero vector"),
+            (&[f32::INFINITY, 1.0], &[1.0, 1.0], "only finite values"),
+            (&[f32::NAN, 1.0], &[1.0, 1.0], "only finite values"),
+        ] {
+            let error = cosine_similarity(a, b).unwrap_err().to_string();
+            assert!(error.contains(expected), "{a:?} {b:?}: {error}");
+        }
+    }
+
+    #[test]
+    fn normalize_scales_to_unit_length() {
+        let mut vector = [3.0f32, 4.0];
+        normalize(&mut vector);
+        assert_eq!(vector, [0.6, 0.8]);
+        let mut zero = [0.0f32, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,88 @@
+//! Typed failures of calls to the OpenAI API.
+//!
+//! Transport and parse failures are returned as an [`OpenAiError`] inside
+//! the `anyhow::Error`, so callers can recover the kind by downcasting (as
+//! with [`PolicyReport`](crate::policy::PolicyReport)) while the messages
+//! stay what they were. `prompt-handle-result` hands the kind to the host.
+
+use std::fmt;
+
+use crate::bindings::exports::wasmcloud::ai::response_handler as wit;
+
+/// Most characters of an error response body kept in an [`OpenAiError`].
+pub(crate) const MAX_BODY_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenAiError {
+    MissingApiKey,
+    /// A non-2xx status other than 429.
+    HttpError {
+        status: u16,
+        body: String,
+    },
+    /// HTTP 429, with the `retry-after` delay when the server sent one.
+    RateLimited {
+        retry_after_secs: Option<u64>,
+    },
+    /// The response was received but could not be read as expected.
+    ParseError(String),
+    /// The request could not be sent or the response body not read.
+    IoError(String),
+}
+
+impl OpenAiError {
+    /// Classifies a non-2xx response.
+    pub fn from_status(status: u16, retry_after: Option<&str>, body: &[u8]) -> Self {
+        if status == 429 {
+            return Self::RateLimited {
+                retry_after_secs: retry_after.and_then(|value| value.trim().parse().ok()),
+            };
+        }
+        let body = String::from_utf8_lossy(body);
+        Self::HttpError {
+            status,
+            body: body.trim().chars().take(MAX_BODY_CHARS).collect(),
+        }
+    }
+}
+
+impl fmt::Display for OpenAiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingApiKey => f.write_str("OPENAI_API_KEY environment variable not set"),
+            Self::HttpError { status, body } if body.is_empty() => {
+                write!(f, "HTTP {status} from OpenAI")
+            }
+            Self::HttpError { status, body } => write!(f, "HTTP {status} from OpenAI: {body}"),
+            Self::RateLimited {
+                retry_after_secs: Some(secs),
+            } => write!(f, "HTTP 429 from OpenAI, retry after {secs}s"),
+            Self::RateLimited {
+                retry_after_secs: None,
+            } => f.write_str("HTTP 429 from OpenAI"),
+            Self::ParseError(message) | Self::IoError(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for OpenAiError {}
+
+impl From<&anyhow::Error> for wit::OpenaiError {
+    /// Failures that are not an [`OpenAiError`] (configuration, policies,
+    /// middleware) are reported as `other` with their full message.
+    fn from(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<OpenAiError>() {
+            Some(OpenAiError::MissingApiKey) => Self::MissingApiKey,
+            Some(OpenAiError::HttpError { status, body }) => Self::HttpError(wit::HttpFailure {
+                status: *status,
+                body: body.clone(),
+            }),
+            Some(OpenAiError::RateLimited { retry_after_secs }) => {
+                Self::RateLimited(*retry_after_secs)
+            }
+            Some(OpenAiError::ParseError(message)) => Self::ParseError(message.clone()),
+            Some(OpenAiError::IoError(message)) => Self::IoError(message.clone()),
+            None => Self::Other(format!("{error:#}")),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Runbook guidance for the error codes the component reports.
+//!
+//! Codes are the `openai-error` kinds of `prompt-handle-result`, the policy
+//! identifiers of [`PolicyReport`](crate::policy::PolicyReport) errors, and
+//! their fixed rule identifiers. [`ErrorCode::ALL`] is generated with the
+//! enum and [`ErrorCode::explanation`] matches every variant exhaustively,
+//! so a new code does not build without its explanation. Policy identifiers
+//! are strings, though: an unknown one is reported as `other`, and the
+//! policy tests check that each policy they produce has a code. Failed
+//! `prompt-handle` calls log their code for looking up here.
+
+use anyhow::{bail, Result};
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit, error::OpenAiError,
+    policy::PolicyReport,
+};
+
+/// Declares [`ErrorCode`] together with [`ErrorCode::ALL`], so no variant
+/// can be left out of the list.
+macro_rules! error_codes {
+    ($($variant:ident,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($variant,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [Self] = &[$(Self::$variant,)*];
+        }
+    };
+}
+
+error_codes! {
+    MissingApiKey,
+    HttpError,
+    RateLimited,
+    ParseError,
+    IoError,
+    Other,
+    CostBudget,
+    RequestBudget,
+    RequestBudgetExceeded,
+    MinimumUsefulCost,
+    ContentFilter,
+    OutputGuard,
+    GuardUnavailable,
+}
+
+#[derive(Debug)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub description: &'static str,
+    pub likely_causes: &'static [&'static str],
+    /// Environment variables that influence the error.
+    pub config: &'static [&'static str],
+    /// Logs and fields to look at first.
+    pub check: &'static [&'static str],
+}
+
+impl ErrorCode {
+    /// The code of a failure returned by the pipeline: its [`OpenAiError`]
+    /// kind, the policy of its [`PolicyReport`], or `other`.
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<OpenAiError>() {
+            return error.code();
+        }
+        error
+            .downcast_ref::<PolicyReport>()
+            .and_then(|report| {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|code| code.explanation().code == report.policy)
+            })
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn explanation(self) -> Explanation {
+        match self {
+            Self::MissingApiKey => Explanation {
+                code: "missing-api-key",
+                description: "The component has no API key to authenticate with, so no request was sent.",
+                likely_causes: &[
+                    "OPENAI_API_KEY is not set in the component's environment",
+                    "the secret was not mapped into the component by the host",
+                ],
+                config: &["OPENAI_API_KEY", "AI_PROVIDER"],
+                check: &["the host's environment and secret configuration for this component"],
+            },
+            Self::HttpError => Explanation {
+                code: "http-error",
+                description: "The API answered with a non-2xx status other than 429.",
+                likely_causes: &[
+                    "401: the API key is invalid or revoked",
+                    "400: an unsupported parameter or model for the endpoint",
+                    "404: a wrong OPENAI_BASE_URL path prefix or an unknown model",
+                    "5xx: a provider outage",
+                    "500 with body INJECTED...: a chaos fault or MOCK_FAILURE_RATE",
+                ],
+                config: &[
+                    "OPENAI_API_KEY",
+                    "OPENAI_BASE_URL",
+                    "OPENAI_API_ENDPOINT",
+                    "OPENAI_MODEL",
+                    "AI_CHAOS_FAULTS",
+                    "MOCK_FAILURE_RATE",
+                ],
+                check: &[
+                    "the status and body of the http-error",
+                    "the \"Using model ... via ...\" log line",
+                ],
+            },
+            Self::RateLimited => Explanation {
+                code: "rate-limited",
+                description: "The API returned HTTP 429: too many requests or tokens for the account's limits.",
+                likely_causes: &[
+                    "a traffic burst above the rate limit",
+                    "exhausted quota or billing limits",
+                    "an injected chaos fault, logged as \"INJECTED chaos fault\" (the error text ends in (INJECTED))",
+                ],
+                config: &["AI_CHAOS_ENABLED", "AI_CHAOS_FAULTS", "OPENAI_SOFT_FAIL"],
+                check: &[
+                    "the retry-after seconds of the rate-limited error",
+                    "the account's usage limits",
+                ],
+            },
+            Self::ParseError => Explanation {
+                code: "parse-error",
+                description: "A response arrived but held no answer the component could extract.",
+                likely_causes: &[
+                    "OPENAI_API_ENDPOINT does not match the API the server speaks",
+                    "the model refused to answer",
+                    "a truncated body, for example from the chaos truncate fault",
+                ],
+                config: &["OPENAI_API_ENDPOINT", "OPENAI_BASE_URL", "AI_CHAOS_FAULTS"],
+                check: &[
+                    "the \"JSON keys\" log line",
+                    "the parse stage of trace-request",
+                ],
+            },
+            Self::IoError => Explanation {
+                code: "io-error",
+                description: "The request could not be sent or the response body could not be read.",
+                likely_causes: &[
+                    "the host does not allow outgoing HTTP to the API host",
+                    "DNS or TLS failure for OPENAI_BASE_URL",
+                    "the connection dropped mid-response",
+                    "an injected chaos timeout (the message ends in (INJECTED))",
+                ],
+                config: &["OPENAI_BASE_URL", "AI_CHAOS_FAULTS"],
+                check: &[
+                    "the host's outgoing HTTP permissions",
+                    "the transport stage of trace-request",
+                ],
+            },
+            Self::Other => Explanation {
+                code: "other",
+                description: "A failure that is not a transport error: configuration, a policy, or middleware.",
+                likely_causes: &[
+                    "an invalid environment variable value",
+                    "a policy rejection, named in the message as \"blocked by ... policy\"",
+                ],
+                config: &[],
+                check: &[
+                    "the message, which names the variable or policy",
+                    "the explanation of the policy code it names",
+                ],
+            },
+            Self::CostBudget => Explanation {
+                code: "cost-budget",
+                description: "The estimated cost of the prompt exceeds the per-prompt budget, so it was not sent.",
+                likely_causes: &[
+                    "a long prompt",
+                    "an expensive model for the configured budget",
+                ],
+                config: &["OPENAI_MAX_COST_USD", "OPENAI_MODEL"],
+                check: &["the estimated cost and budget in the policy detail"],
+            },
+            Self::RequestBudget => Explanation {
+                code: "request-budget",
+                description: "A cost ceiling stopped the request before an upstream call.",
+                likely_causes: &[
+                    "the combined upstream calls of one request exceed its ceiling",
+                    "a budget too small to pay for a useful answer",
+                ],
+                config: &["OPENAI_REQUEST_COST_CEILING_USD", "OPENAI_MAX_OUTPUT_TOKENS"],
+                check: &["the rule in the policy report and its explanation"],
+            },
+            Self::RequestBudgetExceeded => Explanation {
+                code: "request-budget-exceeded",
+                description: "The next upstream call could push the request's total cost over its ceiling.",
+                likely_causes: &[
+                    "an export that makes several upstream calls (guard, revision, fan-out)",
+                    "uncapped output, which is assumed to reach 4096 tokens",
+                ],
+                config: &[
+                    "OPENAI_REQUEST_COST_CEILING_USD",
+                    "OPENAI_MAX_OUTPUT_TOKENS",
+                    "OPENAI_GUARD_ENABLED",
+                ],
+                check: &[
+                    "the call number and committed cost in the policy detail",
+                    "max-cost-usd in the prompt options",
+                ],
+            },
+            Self::MinimumUsefulCost => Explanation {
+                code: "minimum-useful-cost",
+                description: "The budget of prompt-with-budget cannot pay for the input plus 16 output tokens.",
+                likely_causes: &["a budget set too low for the prompt length"],
+                config: &[],
+                check: &["the budget and minimum cost in the policy detail"],
+            },
+            Self::ContentFilter => Explanation {
+                code: "content-filter",
+                description: "The provider's content filter flagged the answer and the policy suppresses it.",
+                likely_causes: &[
+                    "the prompt or answer touches a filtered category",
+                    "the tenant of the structured prompt has the suppress policy",
+                ],
+                config: &[
+                    "OPENAI_CONTENT_FILTER_POLICY",
+                    "OPENAI_CONTENT_FILTER_TENANT_POLICIES",
+                ],
+                check: &[
+                    "the categories listed as rules",
+                    "content_filter_results in the raw response",
+                ],
+            },
+            Self::OutputGuard => Explanation {
+                code: "output-guard",
+                description: "The guard model found disallowed content in the answer and blocked it.",
+                likely_causes: &[
+                    "the answer falls in a configured category",
+                    "an over-broad category list",
+                ],
+                config: &[
+                    "OPENAI_GUARD_ENABLED",
+                    "OPENAI_GUARD_CATEGORIES",
+                    "OPENAI_GUARD_ACTION",
+                ],
+                check: &["the categories listed as rules"],
+            },
+            Self::GuardUnavailable => Explanation {
+                code: "guard-unavailable",
+                description: "The guard model gave no usable verdict, and the guard fails closed.",
+                likely_causes: &[
+                    "the guard model call failed",
+                    "the guard model returned output outside its schema",
+                ],
+                config: &[
+                    "OPENAI_GUARD_MODEL",
+                    "OPENAI_GUARD_FAIL_OPEN",
+                ],
+                check: &["the error in the policy detail", "the guard stage of trace-request"],
+            },
+        }
+    }
+}
+
+impl OpenAiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::MissingApiKey => ErrorCode::MissingApiKey,
+            Self::HttpError { .. } => ErrorCode::HttpError,
+            Self::RateLimited { .. } => ErrorCode::RateLimited,
+            Self::ParseError(_) => ErrorCode::ParseError,
+            Self::IoError(_) => ErrorCode::IoError,
+        }
+    }
+}
+
+/// Looks up `code`, ignoring case and surrounding whitespace.
+pub fn explain_error(code: &str) -> Result<Explanation> {
+    let code = code.trim().to_ascii_lowercase();
+    match ErrorCode::ALL
+        .iter()
+        .map(|code| code.explanation())
+        .find(|explanation| explanation.code == code)
+    {
+        Some(explanation) => Ok(explanation),
+        None => {
+            let known: Vec<&str> = ErrorCode::ALL
+                .iter()
+                .map(|code| code.explanation().code)
+                .collect();
+            bail!(
+                "unknown error code {code:?}, expected one of: {}",
+                known.join(", ")
+            )
+        }
+    }
+}
+
+impl From<Explanation> for wit::ExplanationRecord {
+    fn from(explanation: Explanation) -> Self {
+        let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        Self {
+            code: explanation.code.to_string(),
+            description: explanation.description.to_string(),
+            likely_causes: strings(explanation.likely_causes),
+            config: strings(explanation.config),
+            check: strings(explanation.check),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_is_looked_up_by_its_name() {
+        for &code in ErrorCode::ALL {
+            let name = code.explanation().code;
+            assert_eq!(explain_error(name).unwrap().code, name);
+            assert_eq!(
+                explain_error(&format!(" {} ", name.to_uppercase()))
+                    .unwrap()
+                    .code,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn code_names_are_unique() {
+        let mut names: Vec<&str> = ErrorCode::ALL
+            .iter()
+            .map(|code| code.explanation().code)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), ErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn unknown_codes_list_the_known_ones() {
+        let error = explain_error("teapot").unwrap_err().to_string();
+        assert!(error.contains("unknown error code \"teapot\""), "{error}");
+        assert!(error.contains("missing-api-key, http-error"), "{error}");
+    }
+}
@@ -0,0 +1,76 @@
+//! Feature flag configuration generated from a feature description.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct FeatureFlagConfig {
+    pub flag_name: String,
+    pub description: String,
+    pub default_value: bool,
+    pub rollout_percentage: u8,
+    pub targeting_rules: Vec<String>,
+    pub kill_switch: bool,
+}
+
+const INSTRUCTIONS: &str = "You are a release engineer designing feature flags. \
+Given a feature and a rollout strategy, produce a flag configuration. \
+Use a lowercase snake_case flag name, keep the flag off by default unless the strategy says otherwise, \
+express targeting rules as short human-readable conditions, and set kill_switch to true \
+when the feature should be remotely disableable.";
+
+/// Asks the model for a flag configuration implementing `rollout_strategy`
+/// for `feature_name`.
+pub async fn generate_feature_flags(
+    feature_name: String,
+    rollout_strategy: String,
+) -> Result<FeatureFlagConfig> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "flag_name": { "type": "string" },
+            "description": { "type": "string" },
+            "default_value": { "type": "boolean" },
+            "rollout_percentage": { "type": "integer", "minimum": 0, "maximum": 100 },
+            "targeting_rules": { "type": "array", "items": { "type": "string" } },
+            "kill_switch": { "type": "boolean" }
+        },
+        "required": [
+            "flag_name",
+            "description",
+            "default_value",
+            "rollout_percentage",
+            "targeting_rules",
+            "kill_switch"
+        ],
+        "additionalProperties": false
+    });
+
+    let input = format!("Feature: {feature_name}\nRollout strategy: {rollout_strategy}");
+    let config: FeatureFlagConfig =
+        structured::request("feature_flag_config", INSTRUCTIONS, &input, schema).await?;
+
+    ensure!(
+        config.rollout_percentage <= 100,
+        "rollout_percentage must be at most 100, got {}",
+        config.rollout_percentage
+    );
+
+    Ok(config)
+}
+
+impl From<FeatureFlagConfig> for wit::FeatureFlagConfig {
+    fn from(config: FeatureFlagConfig) -> Self {
+        Self {
+            flag_name: config.flag_name,
+            description: config.description,
+            default_value: config.default_value,
+            rollout_percentage: config.rollout_percentage,
+            targeting_rules: config.targeting_rules,
+            kill_switch: config.kill_switch,
+        }
+    }
+}
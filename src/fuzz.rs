@@ -0,0 +1,394 @@
+//! Seeded fuzzing of the parsers that read untrusted bytes: the Responses
+//! and Chat Completions parsers, the SSE parser and event interpreter, the
+//! error-body classifier, and the stop-sequence cut.
+//!
+//! Inputs come from an in-house xorshift generator, either random bytes or
+//! fixtures mutated by byte flips, insertions of JSON and UTF-8 edge cases,
+//! deletions, and splices. Every target starts from a fixed seed, so a
+//! failure reproduces, and reports the input that caused it. Each target
+//! runs `FUZZ_ITERATIONS` inputs (default 2000); raise it for a longer run.
+//!
+//! Besides never panicking, the targets check properties: answers built
+//! from random text parse back to that text, chunked SSE feeding gives the
+//! same events as one-shot feeding, and a cut answer never contains a stop
+//! sequence.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use serde_json::json;
+
+use crate::{
+    cut_at_stop,
+    error::{OpenAiError, MAX_BODY_CHARS},
+    parse_chat_completions_response, parse_complete_response,
+    streaming::{interpret, SseParser},
+    Endpoint, Usage,
+};
+
+/// xorshift64* (Vigna), enough for reproducible test inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`; `n` must be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    /// Text mixing ASCII, JSON metacharacters, and multi-byte characters.
+    fn text(&mut self, max_chars: usize) -> String {
+        const PIECES: &[&str] = &[
+            "a", "Z", " ", "\n", "\r\n", "\t", "\"", "\\", "/", "{", "}", "[", "]", ":", ",",
+            "\u{0}", "\u{1f}", "\u{7f}", "ü", "ß", "日本", "語", "👍🏽", "e\u{301}", "\u{feff}",
+            "data: ", "[DONE]", "END", "###",
+        ];
+        let chars = self.below(max_chars + 1);
+        (0..chars).map(|_| *self.pick(PIECES)).collect()
+    }
+}
+
+/// Byte sequences that often break hand-written parsers.
+const INTERESTING: &[&[u8]] = &[
+    b"\"",
+    b"\\",
+    b"\\u",
+    b"\\ud800",
+    b"\\udc00\\ud800",
+    b"\\u0000",
+    b"{",
+    b"}",
+    b"[",
+    b"]",
+    b"null",
+    b"-1",
+    b"1e309",
+    b"18446744073709551616",
+    b"\n",
+    b"\r",
+    b"\n\n",
+    b"data:",
+    b"data: [DONE]",
+    b"\xff",
+    b"\xc3",
+    b"\xe6\x97",
+    b"\xf0\x9f\x91",
+];
+
+/// Applies one to four random mutations to `input`, sometimes splicing in
+/// part of `other`.
+fn mutate(rng: &mut Rng, mut input: Vec<u8>, other: &[u8]) -> Vec<u8> {
+    for _ in 0..=rng.below(4) {
+        let at = rng.below(input.len() + 1);
+        match rng.below(6) {
+            0 if !input.is_empty() => {
+                let at = at.min(input.len() - 1);
+                input[at] ^= 1 << rng.below(8);
+            }
+            1 => {
+                let bytes = rng.pick(INTERESTING);
+                input.splice(at..at, bytes.iter().copied());
+            }
+            2 => {
+                let end = (at + rng.below(16)).min(input.len());
+                input.drain(at..end);
+            }
+            3 => {
+                let end = (at + rng.below(32)).min(input.len());
+                let copy = input[at..end].to_vec();
+                input.splice(at..at, copy);
+            }
+            4 if !other.is_empty() => {
+                let start = rng.below(other.len());
+                let end = (start + rng.below(64)).min(other.len());
+                input.splice(at..at, other[start..end].iter().copied());
+            }
+            _ => input.truncate(at),
+        }
+    }
+    input
+}
+
+/// An input for a target: random bytes, or a mutated fixture.
+fn input(rng: &mut Rng, fixtures: &[&str]) -> Vec<u8> {
+    if rng.chance(8) {
+        return rng.bytes(256);
+    }
+    let fixture = rng.pick(fixtures).as_bytes().to_vec();
+    let other = rng.pick(fixtures).as_bytes();
+    mutate(rng, fixture, other)
+}
+
+fn iterations() -> usize {
+    std::env::var("FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(2000)
+}
+
+/// Runs `target` on `iterations()` inputs from `generate`, reporting the
+/// iteration and input of the first failure.
+fn fuzz<T: std::fmt::Debug>(
+    seed: u64,
+    mut generate: impl FnMut(&mut Rng) -> T,
+    target: impl Fn(&T),
+) {
+    let mut rng = Rng::new(seed);
+    for iteration in 0..iterations() {
+        let input = generate(&mut rng);
+        if catch_unwind(AssertUnwindSafe(|| target(&input))).is_err() {
+            panic!("seed {seed:#x}, iteration {iteration}: failed on {input:?}");
+        }
+    }
+}
+
+const RESPONSES: &[&str] = &[
+    r#"{"id":"resp_1","model":"gpt-4o-mini","output":[{"type":"message","content":[{"type":"output_text","text":"Hello"}]}],"usage":{"input_tokens":5,"output_tokens":1,"total_tokens":6}}"#,
+    r#"{"output":[{"type":"reasoning","summary":[]},{"type":"message","content":[{"type":"output_text","text":"a"},{"type":"output_text","text":"b"}]}]}"#,
+    r#"{"output":[{"type":"message","content":[{"type":"refusal","refusal":"no"}]}]}"#,
+    r#"{"output":[{"content":[{"output_text":{"text":"nested"}}]}],"status":"incomplete"}"#,
+    r#"{"error":{"message":"bad","type":"invalid_request_error"}}"#,
+];
+
+const CHAT_COMPLETIONS: &[&str] = &[
+    r#"{"id":"chatcmpl-1","model":"gpt-4o-mini","choices":[{"index":0,"message":{"role":"assistant","content":"Hello"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":1,"total_tokens":6}}"#,
+    r#"{"choices":[{"message":{"role":"assistant","content":null,"refusal":"no"}}]}"#,
+    r#"{"choices":[]}"#,
+];
+
+const STREAMS: &[&str] = &[
+    concat!(
+        "event: response.output_text.delta\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"Grüße \"}\n\n",
+        ": keep-alive\n\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"\\\"日本\\\"\\n\"}\r\n\r\n",
+        "data: {\"type\":\"response.completed\",\"response\":{\"usage\":{\"input_tokens\":1,\"output_tokens\":2}}}\n\n",
+    ),
+    concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"a\\\\b\"}}]}\n\n",
+        "data: first\ndata: second\n\n",
+        "data: [DONE]\n\n",
+    ),
+    "data: {\"type\":\"response.failed\",\"response\":{\"error\":{\"message\":\"x\"}}}\n\n",
+];
+
+#[test]
+fn responses_parser_never_panics() {
+    fuzz(
+        0x5eed_0001,
+        |rng| String::from_utf8_lossy(&input(rng, RESPONSES)).into_owned(),
+        |raw| {
+            let _ = parse_complete_response(raw);
+        },
+    );
+}
+
+#[test]
+fn responses_parser_returns_the_joined_text_parts() {
+    fuzz(
+        0x5eed_0002,
+        |rng| {
+            let parts: Vec<String> = (0..=rng.below(3)).map(|_| rng.text(24)).collect();
+            let reasoning_first = rng.chance(2);
+            (parts, reasoning_first)
+        },
+        |(parts, reasoning_first)| {
+            let content: Vec<_> = parts
+                .iter()
+                .map(|text| json!({"type": "output_text", "text": text}))
+                .collect();
+            let mut output = vec![json!({"type": "message", "content": content})];
+            if *reasoning_first {
+                output.insert(0, json!({"type": "reasoning", "summary": []}));
+            }
+            let raw = json!({"output": output, "usage": {"input_tokens": 1, "output_tokens": 2}});
+            let parsed = parse_complete_response(&raw.to_string()).unwrap();
+            assert_eq!(parsed.text, parts.concat());
+            assert_eq!(parsed.usage.map(|usage| usage.total_tokens), Some(3));
+        },
+    );
+}
+
+#[test]
+fn chat_completions_parser_never_panics() {
+    fuzz(
+        0x5eed_0003,
+        |rng| String::from_utf8_lossy(&input(rng, CHAT_COMPLETIONS)).into_owned(),
+        |raw| {
+            let _ = parse_chat_completions_response(raw);
+        },
+    );
+}
+
+#[test]
+fn chat_completions_parser_returns_the_content() {
+    fuzz(
+        0x5eed_0004,
+        |rng| rng.text(48),
+        |content| {
+            let raw = json!({"choices": [{"message": {"role": "assistant", "content": content}}]});
+            let parsed = parse_chat_completions_response(&raw.to_string()).unwrap();
+            assert_eq!(&parsed.text, content);
+        },
+    );
+}
+
+#[test]
+fn usage_parser_never_panics() {
+    let fixtures: Vec<&str> = RESPONSES.iter().chain(CHAT_COMPLETIONS).copied().collect();
+    fuzz(
+        0x5eed_0005,
+        |rng| input(rng, &fixtures),
+        |raw| {
+            if let Ok(json) = serde_json::from_slice(raw) {
+                for endpoint in [
+                    Endpoint::Responses,
+                    Endpoint::ChatCompletions,
+                    Endpoint::Embeddings,
+                ] {
+                    let _ = Usage::parse(endpoint, &json);
+                }
+            }
+        },
+    );
+}
+
+/// Feeds `stream` in one piece or split at `cuts`, returning every payload.
+fn sse_payloads(stream: &[u8], cuts: &[usize]) -> Vec<String> {
+    let mut parser = SseParser::default();
+    let mut payloads = Vec::new();
+    let mut start = 0;
+    for &cut in cuts.iter().chain([&stream.len()]) {
+        payloads.extend(parser.feed(&stream[start..cut]));
+        start = cut;
+    }
+    payloads.extend(parser.finish());
+    payloads
+}
+
+#[test]
+fn sse_parser_gives_the_same_events_for_any_chunking() {
+    fuzz(
+        0x5eed_0006,
+        |rng| {
+            let stream = input(rng, STREAMS);
+            let mut cuts: Vec<usize> = (0..rng.below(8))
+                .map(|_| rng.below(stream.len() + 1))
+                .collect();
+            cuts.sort_unstable();
+            (stream, cuts)
+        },
+        |(stream, cuts)| {
+            let whole = sse_payloads(stream, &[]);
+            assert_eq!(sse_payloads(stream, cuts), whole);
+            for payload in &whole {
+                let _ = interpret(payload);
+            }
+        },
+    );
+}
+
+#[test]
+fn sse_deltas_round_trip() {
+    fuzz(
+        0x5eed_0007,
+        |rng| {
+            let deltas: Vec<String> = (0..=rng.below(5)).map(|_| rng.text(12)).collect();
+            let cut = rng.next() as usize;
+            (deltas, cut)
+        },
+        |(deltas, cut)| {
+            let stream: String = deltas
+                .iter()
+                .map(|delta| {
+                    let event = json!({"type": "response.output_text.delta", "delta": delta});
+                    format!("data: {event}\n\n")
+                })
+                .collect();
+            let cut = cut % (stream.len() + 1);
+            let received: Vec<String> = sse_payloads(stream.as_bytes(), &[cut])
+                .iter()
+                .filter_map(|payload| match interpret(payload).unwrap() {
+                    crate::streaming::Event::Delta(delta) => Some(delta),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(&received, deltas);
+        },
+    );
+}
+
+#[test]
+fn error_body_classifier_never_panics() {
+    fuzz(
+        0x5eed_0008,
+        |rng| {
+            let status = match rng.below(3) {
+                0 => 429,
+                1 => *rng.pick(&[400, 401, 404, 500, 502, 503]),
+                _ => rng.next() as u16,
+            };
+            let retry_after = rng.chance(2).then(|| {
+                String::from_utf8_lossy(&input(rng, &["1", " 30 ", "-5", "1.5", "x"])).into_owned()
+            });
+            let body = input(rng, RESPONSES);
+            (status, retry_after, body)
+        },
+        |(status, retry_after, body)| match OpenAiError::from_status(
+            *status,
+            retry_after.as_deref(),
+            body,
+        ) {
+            OpenAiError::RateLimited { .. } => assert_eq!(*status, 429),
+            OpenAiError::HttpError { status: kept, body } => {
+                assert_eq!(kept, *status);
+                assert_ne!(kept, 429);
+                assert!(body.chars().count() <= MAX_BODY_CHARS);
+            }
+            other => panic!("unexpected {other:?}"),
+        },
+    );
+}
+
+#[test]
+fn cut_answers_never_contain_a_stop_sequence() {
+    fuzz(
+        0x5eed_0009,
+        |rng| {
+            let stop: Vec<String> = (0..rng.below(5))
+                .map(|_| rng.text(3))
+                .filter(|stop| !stop.is_empty())
+                .collect();
+            (rng.text(40), stop)
+        },
+        |(text, stop)| {
+            let cut = cut_at_stop(text.clone(), stop);
+            assert!(text.starts_with(&cut));
+            for sequence in stop {
+                assert!(!cut.contains(sequence.as_str()), "{sequence:?} in {cut:?}");
+            }
+        },
+    );
+}
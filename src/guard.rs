@@ -0,0 +1,384 @@
+//! Output safety post-check by a second, cheap guard model.
+//!
+//! When `OPENAI_GUARD_ENABLED=true`, every `prompt-handle` answer (never the
+//! prompt) is sent to the guard model with a fixed rubric, which replies
+//! with the disallowed categories it found. Configuration:
+//!
+//! - `OPENAI_GUARD_MODEL`: guard model (default `gpt-4.1-nano`)
+//! - `OPENAI_GUARD_CATEGORIES`: comma-separated disallowed categories
+//!   (default [`DEFAULT_CATEGORIES`])
+//! - `OPENAI_GUARD_ACTION`: `block` (default) fails the request with a
+//!   [`PolicyReport`]; `flag` returns the answer unchanged together with a
+//!   non-blocking [`PolicyReport`]
+//! - `OPENAI_GUARD_FAIL_OPEN=true`: let answers through when the guard
+//!   fails or returns an unusable verdict, instead of blocking them
+//!
+//! The guard call goes through [`fetch`], so it is admitted against and
+//! charged to the request's cost ceiling like any other upstream call; a
+//! ceiling refusal is never failed open. Its cost is logged and traced
+//! separately from the answer's.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    fetch, parse_complete_response,
+    policy::{PolicyReport, PolicyStage},
+    pricing::PricingTable,
+    structured, Endpoint,
+};
+
+const DEFAULT_MODEL: &str = "gpt-4.1-nano";
+
+const DEFAULT_CATEGORIES: &str = "violence,self-harm,sexual,hate,illegal-activity";
+
+/// Output cap of the guard call. A verdict naming every category fits well
+/// within it, and it bounds the worst case the cost ceiling reserves.
+const MAX_OUTPUT_TOKENS: u32 = 256;
+
+const SCHEMA_NAME: &str = "guard_verdict";
+
+const INSTRUCTIONS: &str = "You are a content safety classifier. \
+You are given an AI assistant's answer, not the user's request. List every disallowed category from the allowed list \
+that the answer itself contains, using the category names exactly as given; return an empty list if it contains none. \
+Judge only the answer's content, not the topic it is about.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GuardAction {
+    Block,
+    Flag,
+}
+
+#[derive(Debug, PartialEq)]
+struct GuardConfig {
+    model: String,
+    categories: Vec<String>,
+    action: GuardAction,
+    fail_open: bool,
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    violations: Vec<String>,
+}
+
+fn config_from(var: impl Fn(&str) -> Option<String>) -> Result<Option<GuardConfig>> {
+    if var("OPENAI_GUARD_ENABLED").as_deref() != Some("true") {
+        return Ok(None);
+    }
+
+    let action = match var("OPENAI_GUARD_ACTION").as_deref() {
+        None | Some("block") => GuardAction::Block,
+        Some("flag") => GuardAction::Flag,
+        Some(other) => bail!("OPENAI_GUARD_ACTION must be block or flag, got {other:?}"),
+    };
+    let categories: Vec<String> = var("OPENAI_GUARD_CATEGORIES")
+        .unwrap_or_else(|| DEFAULT_CATEGORIES.to_string())
+        .split(',')
+        .map(|category| category.trim().to_string())
+        .filter(|category| !category.is_empty())
+        .collect();
+    if categories.is_empty() {
+        bail!("OPENAI_GUARD_CATEGORIES must name at least one category");
+    }
+
+    Ok(Some(GuardConfig {
+        model: var("OPENAI_GUARD_MODEL").unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        categories,
+        action,
+        fail_open: var("OPENAI_GUARD_FAIL_OPEN").as_deref() == Some("true"),
+    }))
+}
+
+/// Runs the guard over `answer` when enabled. Returns the report of a
+/// flagged answer, or fails with the report of a blocked one.
+pub(crate) async fn check(answer: &str) -> Result<Option<PolicyReport>> {
+    let Some(config) = config_from(|name| std::env::var(name).ok())? else {
+        return Ok(None);
+    };
+    eprintln!(
+        "[COMPONENT] Guard check with {} for {}",
+        config.model,
+        config.categories.join(", ")
+    );
+    let body = serde_json::to_vec(&request_body(&config, answer))?;
+    judge(&config, fetch(Endpoint::Responses, body).await)
+}
+
+/// The guard model request for `answer`: the categories are both listed in
+/// the input and enforced by the schema.
+fn request_body(config: &GuardConfig, answer: &str) -> Value {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "violations": {
+                "type": "array",
+                "items": { "type": "string", "enum": config.categories }
+            }
+        },
+        "required": ["violations"],
+        "additionalProperties": false
+    });
+    let input = format!(
+        "Disallowed categories: {}\n\nAnswer:\n{answer}",
+        config.categories.join(", ")
+    );
+    let mut body =
+        structured::request_body(&config.model, SCHEMA_NAME, INSTRUCTIONS, &input, schema);
+    body["max_output_tokens"] = json!(MAX_OUTPUT_TOKENS);
+    body
+}
+
+/// Decides on the answer from the guard model's raw `response`, or the
+/// failure to get one.
+fn judge(config: &GuardConfig, response: Result<Vec<u8>>) -> Result<Option<PolicyReport>> {
+    let verdict = match response {
+        // A cost ceiling refusal is a policy decision, not a guard failure.
+        Err(e) if e.downcast_ref::<PolicyReport>().is_some() => return Err(e),
+        Err(e) => Err(e),
+        Ok(raw) => parse_verdict(config, &raw),
+    };
+    let violations = match verdict {
+        // Categories outside the configured list are the guard misbehaving;
+        // they are ignored rather than trusted.
+        Ok(verdict) => verdict
+            .violations
+            .into_iter()
+            .filter(|category| config.categories.contains(category))
+            .collect::<Vec<_>>(),
+        Err(e) if config.fail_open => {
+            eprintln!("[COMPONENT] Guard verdict unusable, failing open: {e:#}");
+            return Ok(None);
+        }
+        Err(e) => return Err(unavailable(&e).reject()),
+    };
+
+    if violations.is_empty() {
+        return Ok(None);
+    }
+    match config.action {
+        GuardAction::Flag => {
+            eprintln!(
+                "[COMPONENT] Guard flagged the answer: {}",
+                violations.join(", ")
+            );
+            Ok(Some(flagged(violations)))
+        }
+        GuardAction::Block => Err(blocked(violations).reject()),
+    }
+}
+
+fn parse_verdict(config: &GuardConfig, raw: &[u8]) -> Result<Verdict> {
+    let raw = std::str::from_utf8(raw).map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+    if let Some(usage) = parse_complete_response(raw)?.usage {
+        let cost_usd = PricingTable::OPENAI
+            .lookup(&config.model)
+            .map(|pricing| pricing.cost(usage.input_tokens, usage.output_tokens));
+        eprintln!(
+            "[COMPONENT] Guard check used {} input and {} output tokens (${:.6}), separate from the answer",
+            usage.input_tokens,
+            usage.output_tokens,
+            cost_usd.unwrap_or_default()
+        );
+    }
+    structured::parse_output(SCHEMA_NAME, raw)
+}
+
+fn flagged(violations: Vec<String>) -> PolicyReport {
+    PolicyReport {
+        policy: "output-guard",
+        detail: format!(
+            "the guard model found disallowed content ({})",
+            violations.join(", ")
+        ),
+        rules: violations,
+        stage: PolicyStage::PostReceive,
+        remediation: "Review the answer before showing it, or set OPENAI_GUARD_ACTION=block to drop flagged answers.",
+    }
+}
+
+pub(crate) fn unavailable(error: &anyhow::Error) -> PolicyReport {
+    PolicyReport {
+        policy: "output-guard",
+        rules: vec!["guard-unavailable".to_string()],
+        stage: PolicyStage::PostReceive,
+        detail: format!("the guard model gave no usable verdict: {error:#}"),
+        remediation:
+            "Retry, or set OPENAI_GUARD_FAIL_OPEN=true to pass answers the guard cannot judge.",
+    }
+}
+
+pub(crate) fn blocked(violations: Vec<String>) -> PolicyReport {
+    PolicyReport {
+        policy: "output-guard",
+        detail: format!(
+            "the guard model found disallowed content ({})",
+            violations.join(", ")
+        ),
+        rules: violations,
+        stage: PolicyStage::PostReceive,
+        remediation: "Rephrase the prompt to avoid the blocked categories.",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{error::OpenAiError, mock, request_budget::Ledger};
+
+    fn config(action: GuardAction, fail_open: bool) -> GuardConfig {
+        GuardConfig {
+            model: DEFAULT_MODEL.to_string(),
+            categories: vec!["hate".to_string(), "violence".to_string()],
+            action,
+            fail_open,
+        }
+    }
+
+    /// What the mock provider answers to the guard request for `answer`.
+    fn mock_reply(config: &GuardConfig, answer: &str) -> Result<Vec<u8>> {
+        let body = serde_json::to_vec(&request_body(config, answer)).unwrap();
+        futures::executor::block_on(mock::respond(Endpoint::Responses, &body))
+    }
+
+    /// A Responses API reply whose answer is `text`.
+    fn reply(text: &str) -> Result<Vec<u8>> {
+        Ok(json!({
+            "output": [{
+                "type": "message",
+                "content": [{ "type": "output_text", "text": text }]
+            }],
+            "usage": { "input_tokens": 80, "output_tokens": 6, "total_tokens": 86 }
+        })
+        .to_string()
+        .into_bytes())
+    }
+
+    fn policy_report(error: anyhow::Error) -> PolicyReport {
+        error.downcast::<PolicyReport>().expect("a policy report")
+    }
+
+    #[test]
+    fn flags_the_mock_verdict_without_blocking() {
+        // The mock provider answers with the first category the schema allows.
+        let config = config(GuardAction::Flag, false);
+        let report = judge(&config, mock_reply(&config, "Some answer"))
+            .unwrap()
+            .expect("flagged");
+        assert_eq!(report.policy, "output-guard");
+        assert_eq!(report.rules, ["hate"]);
+        assert_eq!(report.stage, PolicyStage::PostReceive);
+    }
+
+    #[test]
+    fn blocks_the_mock_verdict() {
+        let config = config(GuardAction::Block, true);
+        let error = judge(&config, mock_reply(&config, "Some answer")).unwrap_err();
+        let report = policy_report(error);
+        assert_eq!(report.policy, "output-guard");
+        assert_eq!(report.rules, ["hate"]);
+    }
+
+    #[test]
+    fn passes_clean_verdicts_and_ignores_unknown_categories() {
+        for action in [GuardAction::Block, GuardAction::Flag] {
+            let config = config(action, false);
+            for text in [r#"{"violations": []}"#, r#"{"violations": ["spam"]}"#] {
+                assert!(judge(&config, reply(text)).unwrap().is_none(), "{text}");
+            }
+            let mixed = judge(&config, reply(r#"{"violations": ["spam", "violence"]}"#));
+            match action {
+                GuardAction::Flag => assert_eq!(mixed.unwrap().unwrap().rules, ["violence"]),
+                GuardAction::Block => {
+                    assert_eq!(policy_report(mixed.unwrap_err()).rules, ["violence"])
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unusable_verdicts_follow_the_fail_open_setting() {
+        let unusable = || {
+            vec![
+                reply("I think this answer is fine."),
+                reply(r#"{"verdict": "safe"}"#),
+                reply(r#"{"violations": "hate"}"#),
+                Ok(b"{\"output\": [".to_vec()),
+                Ok(vec![0xff, 0xfe]),
+                Err(OpenAiError::HttpError {
+                    status: 500,
+                    body: String::new(),
+                }
+                .into()),
+            ]
+        };
+        for output in unusable() {
+            let error = judge(&config(GuardAction::Flag, false), output).unwrap_err();
+            let report = policy_report(error);
+            assert_eq!(report.rules, ["guard-unavailable"]);
+        }
+        for output in unusable() {
+            assert!(judge(&config(GuardAction::Block, true), output)
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn never_fails_open_on_a_cost_ceiling_refusal() {
+        let mut ledger = Ledger {
+            ceiling_usd: Some(0.000_001),
+            ..Ledger::default()
+        };
+        let refusal = ledger.admit(0.01).unwrap_err().reject();
+        let report =
+            policy_report(judge(&config(GuardAction::Block, true), Err(refusal)).unwrap_err());
+        assert_eq!(report.policy, "request-budget");
+    }
+
+    #[test]
+    fn caps_the_guard_request_and_constrains_its_categories() {
+        let body = request_body(&config(GuardAction::Block, false), "The answer.");
+        assert_eq!(body["model"], DEFAULT_MODEL);
+        assert_eq!(body["max_output_tokens"], MAX_OUTPUT_TOKENS);
+        assert_eq!(
+            body.pointer("/text/format/schema/properties/violations/items/enum"),
+            Some(&json!(["hate", "violence"]))
+        );
+        let input = body["input"].as_str().unwrap();
+        assert!(input.starts_with("Disallowed categories: hate, violence"));
+        assert!(input.ends_with("Answer:\nThe answer."));
+    }
+
+    #[test]
+    fn reads_the_config_behind_the_switch() {
+        let vars = |pairs: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+        assert_eq!(config_from(vars(&[])).unwrap(), None);
+
+        let config = config_from(vars(&[
+            ("OPENAI_GUARD_ENABLED", "true"),
+            ("OPENAI_GUARD_ACTION", "flag"),
+            ("OPENAI_GUARD_CATEGORIES", " hate, ,violence "),
+            ("OPENAI_GUARD_FAIL_OPEN", "true"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(config, self::config(GuardAction::Flag, true));
+
+        for (name, value) in [
+            ("OPENAI_GUARD_ACTION", "warn"),
+            ("OPENAI_GUARD_CATEGORIES", " , "),
+        ] {
+            assert!(config_from(vars(&[("OPENAI_GUARD_ENABLED", "true"), (name, value)])).is_err());
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Infrastructure-as-code templates generated from architecture descriptions.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Clone, Copy)]
+pub enum IacProvider {
+    Terraform,
+    Pulumi,
+    Cdk,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CloudProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl CloudProvider {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Aws => "AWS",
+            Self::Gcp => "Google Cloud",
+            Self::Azure => "Azure",
+        }
+    }
+
+    fn terraform_provider(self) -> &'static str {
+        match self {
+            Self::Aws => "aws",
+            Self::Gcp => "google",
+            Self::Azure => "azurerm",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IacResult {
+    pub code: String,
+    pub variables: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+const INSTRUCTIONS: &str = "You are a cloud infrastructure engineer. \
+Write infrastructure-as-code for the described architecture using the requested tool and cloud. \
+Put the complete program in code, list the names of input variables or config values the operator must supply \
+in variables, and record assumptions and follow-up steps in notes.";
+
+/// Asks the model for an IaC template and checks that it opens with the
+/// boilerplate expected for `provider`.
+pub async fn generate_iac(
+    architecture_description: String,
+    provider: IacProvider,
+    cloud: CloudProvider,
+) -> Result<IacResult> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "code": { "type": "string" },
+            "variables": { "type": "array", "items": { "type": "string" } },
+            "notes": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["code", "variables", "notes"],
+        "additionalProperties": false
+    });
+
+    let tool = match provider {
+        IacProvider::Terraform => format!(
+            "Terraform HCL, starting with a terraform block that requires the {} provider",
+            cloud.terraform_provider()
+        ),
+        IacProvider::Pulumi => "a Pulumi program in TypeScript".to_string(),
+        IacProvider::Cdk => match cloud {
+            CloudProvider::Aws => "an AWS CDK app in TypeScript using aws-cdk-lib".to_string(),
+            _ => "a CDK for Terraform (cdktf) app in TypeScript".to_string(),
+        },
+    };
+    let input = format!(
+        "Tool: {tool}\nCloud: {}\nArchitecture: {architecture_description}",
+        cloud.name()
+    );
+    let result: IacResult = structured::request("iac_result", INSTRUCTIONS, &input, schema).await?;
+
+    check_boilerplate(&result.code, provider)?;
+
+    Ok(result)
+}
+
+fn check_boilerplate(code: &str, provider: IacProvider) -> Result<()> {
+    // Skip leading blank and comment lines before looking at the first statement.
+    let first = code
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with('#'))
+        .unwrap_or("");
+
+    match provider {
+        IacProvider::Terraform => ensure!(
+            first.starts_with("terraform {") || first.starts_with("provider \""),
+            "Terraform code must start with a terraform or provider block"
+        ),
+        IacProvider::Pulumi => ensure!(
+            first.starts_with("import") && code.contains("@pulumi/"),
+            "Pulumi code must start by importing the @pulumi packages"
+        ),
+        IacProvider::Cdk => ensure!(
+            first.starts_with("import") && (code.contains("aws-cdk-lib") || code.contains("cdktf")),
+            "CDK code must start by importing aws-cdk-lib or cdktf"
+        ),
+    }
+
+    Ok(())
+}
+
+impl From<wit::IacProvider> for IacProvider {
+    fn from(provider: wit::IacProvider) -> Self {
+        match provider {
+            wit::IacProvider::Terraform => Self::Terraform,
+            wit::IacProvider::Pulumi => Self::Pulumi,
+            wit::IacProvider::Cdk => Self::Cdk,
+        }
+    }
+}
+
+impl From<wit::CloudProvider> for CloudProvider {
+    fn from(cloud: wit::CloudProvider) -> Self {
+        match cloud {
+            wit::CloudProvider::Aws => Self::Aws,
+            wit::CloudProvider::Gcp => Self::Gcp,
+            wit::CloudProvider::Azure => Self::Azure,
+        }
+    }
+}
+
+impl From<IacResult> for wit::IacResult {
+    fn from(result: IacResult) -> Self {
+        Self {
+            code: result.code,
+            variables: result.variables,
+            notes: result.notes,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Questions about several images answered in one multimodal request.
+
+use anyhow::{anyhow, ensure, Result};
+use serde_json::{json, Value};
+
+use crate::{base64, fetch, parse_complete_response, Endpoint, MODEL};
+
+/// Upper bound on images per request.
+const MAX_IMAGES: usize = 10;
+
+/// OpenAI's limit on the total base64 image payload of one request.
+const MAX_PAYLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+/// Sends `images` as numbered, inline base64 images followed by `question`
+/// and returns the model's analysis across all of them.
+pub async fn analyze_image_grid(images: Vec<Vec<u8>>, question: String) -> Result<String> {
+    ensure!(!images.is_empty(), "no images to analyze");
+    ensure!(
+        images.len() <= MAX_IMAGES,
+        "at most {MAX_IMAGES} images are allowed, got {}",
+        images.len()
+    );
+    let payload: usize = images
+        .iter()
+        .map(|image| base64::encoded_len(image.len()))
+        .sum();
+    ensure!(
+        payload < MAX_PAYLOAD_BYTES,
+        "base64 image payload is {payload} bytes, the limit is {MAX_PAYLOAD_BYTES}"
+    );
+
+    let mut content = Vec::with_capacity(images.len() * 2 + 1);
+    for (index, image) in images.iter().enumerate() {
+        let mime = mime_type(image)
+            .ok_or_else(|| anyhow!("image {} is not a PNG, JPEG, GIF, or WebP image", index + 1))?;
+        content.push(json!({
+            "type": "input_image",
+            "image_url": format!("data:{mime};base64,{}", base64::encode(image)),
+        }));
+        content.push(json!({ "type": "input_text", "text": format!("Image {}", index + 1) }));
+    }
+    content.push(json!({ "type": "input_text", "text": question }));
+
+    let body = json!({
+        "model": MODEL,
+        "input": [{ "role": "user", "content": Value::Array(content) }],
+        "stream": false,
+    });
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+
+    parse_complete_response(&raw_response).map(|parsed| parsed.text)
+}
+
+/// Detects the image format from its magic bytes.
+fn mime_type(image: &[u8]) -> Option<&'static str> {
+    match image {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+//! Technical interview questions generated from a job description.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+/// Upper bound on questions generated per call.
+const MAX_QUESTIONS: u8 = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Seniority {
+    Junior,
+    Mid,
+    Senior,
+    Staff,
+    Principal,
+}
+
+impl Seniority {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Junior => "junior",
+            Self::Mid => "mid-level",
+            Self::Senior => "senior",
+            Self::Staff => "staff",
+            Self::Principal => "principal",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InterviewQuestion {
+    pub question: String,
+    pub category: String,
+    pub expected_answer_points: Vec<String>,
+    pub follow_ups: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct InterviewQuestions {
+    questions: Vec<InterviewQuestion>,
+}
+
+const INSTRUCTIONS: &str = "You are an experienced technical interviewer. \
+Write interview questions for the job description, calibrated to the requested seniority. \
+Spread the questions across categories such as coding, system design, debugging, and collaboration, \
+list the points a strong answer covers, and suggest follow-up questions that probe deeper.";
+
+/// Asks the model for `count` interview questions for `job_description`.
+pub async fn generate_interview_questions(
+    job_description: String,
+    seniority: Seniority,
+    count: u8,
+) -> Result<Vec<InterviewQuestion>> {
+    ensure!(
+        (1..=MAX_QUESTIONS).contains(&count),
+        "count must be between 1 and {MAX_QUESTIONS}, got {count}"
+    );
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "questions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "question": { "type": "string" },
+                        "category": { "type": "string" },
+                        "expected_answer_points": { "type": "array", "items": { "type": "string" } },
+                        "follow_ups": { "type": "array", "items": { "type": "string" } }
+                    },
+                    "required": ["question", "category", "expected_answer_points", "follow_ups"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["questions"],
+        "additionalProperties": false
+    });
+
+    let input = format!(
+        "Seniority: {}\nNumber of questions: {count}\nJob description: {job_description}",
+        seniority.name()
+    );
+    let mut questions: InterviewQuestions =
+        structured::request("interview_questions", INSTRUCTIONS, &input, schema).await?;
+
+    questions.questions.truncate(count.into());
+    Ok(questions.questions)
+}
+
+impl From<wit::Seniority> for Seniority {
+    fn from(seniority: wit::Seniority) -> Self {
+        match seniority {
+            wit::Seniority::Junior => Self::Junior,
+            wit::Seniority::Mid => Self::Mid,
+            wit::Seniority::Senior => Self::Senior,
+            wit::Seniority::Staff => Self::Staff,
+            wit::Seniority::Principal => Self::Principal,
+        }
+    }
+}
+
+impl From<InterviewQuestion> for wit::InterviewQuestion {
+    fn from(question: InterviewQuestion) -> Self {
+        Self {
+            question: question.question,
+            category: question.category,
+            expected_answer_points: question.expected_answer_points,
+            follow_ups: question.follow_ups,
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! Local JSON Schema validation.
+//!
+//! Covers the keywords used by OpenAPI response schemas and structured
+//! output schemas: `type` (including OpenAPI 3.0 `nullable`), `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`,
+//! `allOf`/`anyOf`/`oneOf`, numeric bounds, string and array lengths, and
+//! local `$ref`s resolved against a root document. Unknown keywords are
+//! ignored.
+
+use serde_json::Value;
+
+/// One validation failure. `field` is a path such as `$.items[2].id`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub message: String,
+}
+
+/// Guards against cyclic `$ref`s.
+const MAX_DEPTH: usize = 64;
+
+/// Validates `instance` against `schema`, resolving `$ref`s against `root`.
+pub fn validate(schema: &Value, instance: &Value, root: &Value) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    Validator {
+        root,
+        issues: &mut issues,
+    }
+    .check(schema, instance, "$", 0);
+    issues
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    issues: &'a mut Vec<ValidationIssue>,
+}
+
+impl Validator<'_> {
+    fn issue(&mut self, field: &str, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Runs `check` into a scratch list and reports whether it passed.
+    fn passes(&mut self, schema: &Value, instance: &Value, field: &str, depth: usize) -> bool {
+        let mut scratch = Vec::new();
+        Validator {
+            root: self.root,
+            issues: &mut scratch,
+        }
+        .check(schema, instance, field, depth);
+        scratch.is_empty()
+    }
+
+    fn check(&mut self, schema: &Value, instance: &Value, field: &str, depth: usize) {
+        if depth > MAX_DEPTH {
+            self.issue(field, "schema nesting too deep (cyclic $ref?)");
+            return;
+        }
+        let Some(schema_object) = schema.as_object() else {
+            // `true`/`false` boolean schemas.
+            if schema == &Value::Bool(false) {
+                self.issue(field, "no value is allowed here");
+            }
+            return;
+        };
+
+        if let Some(reference) = schema_object.get("$ref").and_then(Value::as_str) {
+            match resolve(self.root, reference) {
+                Some(target) => self.check(target, instance, field, depth + 1),
+                None => self.issue(field, format!("unresolvable $ref {reference}")),
+            }
+            return;
+        }
+
+        if instance.is_null() && schema_object.get("nullable") == Some(&Value::Bool(true)) {
+            return;
+        }
+
+        if let Some(types) = schema_object.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|name| matches_type(name, instance)) {
+                self.issue(
+                    field,
+                    format!(
+                        "expected {}, got {}",
+                        allowed.join(" or "),
+                        type_name(instance)
+                    ),
+                );
+                return;
+            }
+        }
+
+        if let Some(options) = schema_object.get("enum").and_then(Value::as_array) {
+            if !options.contains(instance) {
+                self.issue(
+                    field,
+                    format!("{instance} is not one of the allowed values"),
+                );
+            }
+        }
+        if let Some(expected) = schema_object.get("const") {
+            if expected != instance {
+                self.issue(field, format!("expected {expected}"));
+            }
+        }
+
+        for sub in schema_object
+            .get("allOf")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check(sub, instance, field, depth + 1);
+        }
+        if let Some(options) = schema_object.get("anyOf").and_then(Value::as_array) {
+            if !options
+                .iter()
+                .any(|sub| self.passes(sub, instance, field, depth + 1))
+            {
+                self.issue(field, "does not match any allowed schema (anyOf)");
+            }
+        }
+        if let Some(options) = schema_object.get("oneOf").and_then(Value::as_array) {
+            let matching = options
+                .iter()
+                .filter(|sub| self.passes(sub, instance, field, depth + 1))
+                .count();
+            if matching != 1 {
+                self.issue(
+                    field,
+                    format!("matches {matching} schemas, expected exactly one (oneOf)"),
+                );
+            }
+        }
+
+        match instance {
+            Value::Object(object) => {
+                let properties = schema_object.get("properties").and_then(Value::as_object);
+                for name in schema_object
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Some(name) = name.as_str() {
+                        if !object.contains_key(name) {
+                            self.issue(&format!("{field}.{name}"), "required field is missing");
+                        }
+                    }
+                }
+                for (name, value) in object {
+                    let path = format!("{field}.{name}");
+                    match properties.and_then(|properties| properties.get(name)) {
+                        Some(property) => self.check(property, value, &path, depth + 1),
+                        None => match schema_object.get("additionalProperties") {
+                            Some(Value::Bool(false)) => self.issue(&path, "unexpected field"),
+                            Some(additional @ Value::Object(_)) => {
+                                self.check(additional, value, &path, depth + 1)
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = schema_object.get("minItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) < min {
+                        self.issue(
+                            field,
+                            format!("expected at least {min} items, got {}", items.len()),
+                        );
+                    }
+                }
+                if let Some(max) = schema_object.get("maxItems").and_then(Value::as_u64) {
+                    if items.len() as u64 > max {
+                        self.issue(
+                            field,
+                            format!("expected at most {max} items, got {}", items.len()),
+                        );
+                    }
+                }
+                if let Some(item_schema) = schema_object.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{field}[{index}]"), depth + 1);
+                    }
+                }
+            }
+            Value::String(text) => {
+                let length = text.chars().count() as u64;
+                if let Some(min) = schema_object.get("minLength").and_then(Value::as_u64) {
+                    if length < min {
+                        self.issue(
+                            field,
+                            format!("expected at least {min} characters, got {length}"),
+                        );
+                    }
+                }
+                if let Some(max) = schema_object.get("maxLength").and_then(Value::as_u64) {
+                    if length > max {
+                        self.issue(
+                            field,
+                            format!("expected at most {max} characters, got {length}"),
+                        );
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let value = number.as_f64().unwrap_or(f64::NAN);
+                let bound = |name: &str| schema_object.get(name).and_then(Value::as_f64);
+                if let Some(min) = bound("minimum") {
+                    if value < min {
+                        self.issue(field, format!("{value} is below the minimum {min}"));
+                    }
+                }
+                if let Some(max) = bound("maximum") {
+                    if value > max {
+                        self.issue(field, format!("{value} is above the maximum {max}"));
+                    }
+                }
+                if let Some(min) = bound("exclusiveMinimum") {
+                    if value <= min {
+                        self.issue(field, format!("{value} must be greater than {min}"));
+                    }
+                }
+                if let Some(max) = bound("exclusiveMaximum") {
+                    if value >= max {
+                        self.issue(field, format!("{value} must be less than {max}"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Resolves a local reference such as `#/components/schemas/User`.
+pub fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    root.pointer(pointer)
+}
+
+fn matches_type(name: &str, instance: &Value) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        "integer" => instance.as_f64().is_some_and(|value| value.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Fields and messages of the issues for `instance` against `schema`,
+    /// which is also its own root.
+    fn issues(schema: Value, instance: Value) -> Vec<(String, String)> {
+        validate(&schema, &instance, &schema)
+            .into_iter()
+            .map(|issue| (issue.field, issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn validates_the_supported_keywords() {
+        let cases = [
+            // (schema, instance, expected issues as (field, message fragment))
+            (json!({"type": "integer"}), json!(3), vec![]),
+            (json!({"type": "integer"}), json!(3.0), vec![]),
+            (
+                json!({"type": "integer"}),
+                json!(3.5),
+                vec![("$", "expected integer, got number")],
+            ),
+            (
+                json!({"type": "integer"}),
+                json!("3"),
+                vec![("$", "expected integer, got string")],
+            ),
+            (json!({"type": "number"}), json!(3), vec![]),
+            (json!({"type": ["string", "null"]}), json!(null), vec![]),
+            (
+                json!({"type": "string"}),
+                json!(null),
+                vec![("$", "expected string, got null")],
+            ),
+            (
+                json!({"type": "string", "nullable": true}),
+                json!(null),
+                vec![],
+            ),
+            (
+                json!({"type": "string", "nullable": true}),
+                json!(1),
+                vec![("$", "expected string, got number")],
+            ),
+            (
+                json!({"type": "string", "nullable": false}),
+                json!(null),
+                vec![("$", "got null")],
+            ),
+            (
+                json!({"enum": ["a", "b"]}),
+                json!("c"),
+                vec![("$", "not one of the allowed values")],
+            ),
+            (json!({"const": 1}), json!(2), vec![("$", "expected 1")]),
+            (
+                json!({"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}, "additionalProperties": false}),
+                json!({"name": "x"}),
+                vec![
+                    ("$.id", "required field is missing"),
+                    ("$.name", "unexpected field"),
+                ],
+            ),
+            (
+                json!({"additionalProperties": {"type": "number"}}),
+                json!({"a": 1, "b": "2"}),
+                vec![("$.b", "expected number, got string")],
+            ),
+            (
+                json!({"items": {"type": "string"}, "minItems": 2, "maxItems": 3}),
+                json!(["a", 1]),
+                vec![("$[1]", "expected string, got number")],
+            ),
+            (
+                json!({"minItems": 2}),
+                json!([1]),
+                vec![("$", "at least 2 items, got 1")],
+            ),
+            (
+                json!({"maxItems": 1}),
+                json!([1, 2]),
+                vec![("$", "at most 1 items, got 2")],
+            ),
+            (
+                json!({"minLength": 2}),
+                json!("é"),
+                vec![("$", "at least 2 characters, got 1")],
+            ),
+            (
+                json!({"maxLength": 2}),
+                json!("日本語"),
+                vec![("$", "at most 2 characters, got 3")],
+            ),
+            (
+                json!({"minimum": 1, "maximum": 2}),
+                json!(0),
+                vec![("$", "below the minimum 1")],
+            ),
+            (
+                json!({"minimum": 1, "maximum": 2}),
+                json!(3),
+                vec![("$", "above the maximum 2")],
+            ),
+            (
+                json!({"exclusiveMinimum": 1}),
+                json!(1),
+                vec![("$", "must be greater than 1")],
+            ),
+            (
+                json!({"exclusiveMaximum": 1}),
+                json!(1),
+                vec![("$", "must be less than 1")],
+            ),
+            (
+                json!({"allOf": [{"minimum": 0}, {"maximum": 1}]}),
+                json!(2),
+                vec![("$", "above the maximum 1")],
+            ),
+            (
+                json!({"anyOf": [{"type": "string"}, {"type": "integer"}]}),
+                json!(1),
+                vec![],
+            ),
+            (
+                json!({"anyOf": [{"type": "string"}, {"type": "integer"}]}),
+                json!(true),
+                vec![("$", "(anyOf)")],
+            ),
+            (
+                json!({"oneOf": [{"type": "integer"}, {"minimum": 5}]}),
+                json!(1),
+                vec![],
+            ),
+            (
+                json!({"oneOf": [{"type": "integer"}, {"minimum": 5}]}),
+                json!(7),
+                vec![("$", "matches 2 schemas")],
+            ),
+            (
+                json!({"oneOf": [{"type": "string"}, {"type": "boolean"}]}),
+                json!(1),
+                vec![("$", "matches 0 schemas")],
+            ),
+            (json!(true), json!({"anything": 1}), vec![]),
+            (
+                json!({"properties": {"x": false}}),
+                json!({"x": 1}),
+                vec![("$.x", "no value is allowed here")],
+            ),
+            (json!({"x-unknown-keyword": 1}), json!(1), vec![]),
+        ];
+        for (schema, instance, expected) in cases {
+            let found = issues(schema.clone(), instance.clone());
+            assert_eq!(
+                found.len(),
+                expected.len(),
+                "{schema} / {instance}: {found:?}"
+            );
+            for ((field, message), (expected_field, fragment)) in found.iter().zip(&expected) {
+                assert_eq!(field, expected_field, "{schema} / {instance}");
+                assert!(
+                    message.contains(fragment),
+                    "{schema} / {instance}: {message}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_local_refs() {
+        let root = json!({
+            "components": {"schemas": {
+                "User": {"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}},
+                "a/b": {"type": "string"}
+            }}
+        });
+        let user = json!({"$ref": "#/components/schemas/User"});
+        assert!(validate(&user, &json!({"id": 1}), &root).is_empty());
+        let found = validate(&user, &json!({"id": "1"}), &root);
+        assert_eq!(found[0].field, "$.id");
+
+        let escaped = json!({"$ref": "#/components/schemas/a~1b"});
+        assert!(validate(&escaped, &json!("x"), &root).is_empty());
+
+        let missing = json!({"$ref": "#/components/schemas/Missing"});
+        let found = validate(&missing, &json!(1), &root);
+        assert_eq!(
+            found[0].message,
+            "unresolvable $ref #/components/schemas/Missing"
+        );
+        let remote = json!({"$ref": "other.json#/User"});
+        assert_eq!(validate(&remote, &json!(1), &root).len(), 1);
+    }
+
+    #[test]
+    fn cyclic_refs_stop_at_the_depth_limit() {
+        let root = json!({"definitions": {
+            "loop": {"$ref": "#/definitions/loop"},
+            "ping": {"$ref": "#/definitions/pong"},
+            "pong": {"$ref": "#/definitions/ping"},
+            "tree": {
+                "type": "object",
+                "properties": {"children": {"type": "array", "items": {"$ref": "#/definitions/tree"}}}
+            }
+        }});
+        for name in ["loop", "ping"] {
+            let schema = json!({"$ref": format!("#/definitions/{name}")});
+            let found = validate(&schema, &json!(1), &root);
+            assert_eq!(found.len(), 1, "{name}");
+            assert!(found[0].message.contains("cyclic $ref"), "{name}");
+        }
+
+        // Recursive schemas are fine as long as the instance is finite.
+        let tree = json!({"$ref": "#/definitions/tree"});
+        let instance = json!({"children": [{"children": []}, {"children": [{"children": 1}]}]});
+        let found = validate(&tree, &instance, &root);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].field, "$.children[1].children[0].children");
+    }
+}
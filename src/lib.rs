@@ -1,100 +1,1281 @@
+use std::ops::{ControlFlow, RangeInclusive};
+
 use anyhow::{anyhow, bail, Result};
+use audit_wrap::audit_wrap;
 use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use url::Url;
 
+use error::OpenAiError;
+use middleware::PromptRequest;
+use policy::PolicyReport;
+use trace::Trace;
+
+mod ab_test;
+mod alerts;
+mod answer_score;
+mod audit;
+mod authorship;
+mod base64;
+mod budget;
+mod capabilities;
+mod chaos;
+mod compare;
+mod content_filter;
+mod cost;
+mod debate;
+mod embeddings;
+mod error;
+mod explain;
+mod feature_flags;
+#[cfg(test)]
+mod fuzz;
+mod guard;
+mod iac;
+mod image_grid;
+mod interview;
+mod json_schema;
+mod locale_format;
+mod localize;
+pub mod middleware;
+mod mock;
+mod onboarding;
+mod openapi;
+pub mod policy;
+mod pricing;
+mod receipt;
+mod refine;
+mod request_budget;
+mod request_hash;
+mod rerank;
+mod sha256;
+mod soft_fail;
+mod streaming;
+mod structured;
+mod system_prompt;
+mod table;
+mod thinking;
+mod tokens;
+mod trace;
+mod training_data;
+mod watermark;
+mod wit_gen;
+mod workflow;
+mod yaml;
+
 mod bindings {
     wit_bindgen::generate!({
         world: "ai",
         generate_all,
+        additional_derives: [serde::Serialize],
+        additional_derives_ignore: ["stream-error"],
     });
 }
 
 use bindings::{
-    exports::wasmcloud::ai::response_handler::Guest,
+    exports::wasmcloud::ai::response_handler::{
+        AbVariant, AlertRule, AnswerScore, AuthorshipResult, BudgetedResponse, CapabilityReport,
+        CloudProvider, CostEstimate, DebateJudgment, EmbeddedChunk, EmbeddingOptions,
+        ExplanationRecord, FeatureFlagConfig, Guest, HandledResponse, IacProvider, IacResult,
+        InterviewQuestion, LocalizedResponse, ModelResponse, OnboardingPlan, OpenaiError,
+        PromptOptions, ReceiptedResponse, RerankResult, Seniority, TableData, ThinkingResponse,
+        TraceReport, TrainingFormat, ValidationIssue, WorkflowEngine,
+    },
     wasi::http::types::{Fields, IncomingResponse, Method, OutgoingRequest, Scheme},
 };
 
 struct Component;
 
+#[audit_wrap]
 impl Guest for Component {
     fn prompt_handle(prompt: String) -> String {
-        executor::run(async move { handle_request(prompt).await })
+        executor::run(async move { handle_prompt(prompt, &mut Trace::default()).await })
+    }
+
+    fn generate_flag_config(
+        feature: String,
+        strategy: String,
+    ) -> Result<FeatureFlagConfig, String> {
+        executor::run(async move { feature_flags::generate_feature_flags(feature, strategy).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_alerts(system: String, sla_json: String) -> Result<Vec<AlertRule>, String> {
+        executor::run(async move {
+            let sla = serde_json::from_str(&sla_json)
+                .map_err(|e| anyhow!("invalid SLA configuration: {e}"))?;
+            alerts::generate_alerts(system, sla).await
+        })
+        .map(|rules| rules.into_iter().map(Into::into).collect())
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_workflow(desc: String, engine: WorkflowEngine) -> Result<String, String> {
+        executor::run(async move { workflow::generate_workflow(desc, engine.into()).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_iac(
+        desc: String,
+        provider: IacProvider,
+        cloud: CloudProvider,
+    ) -> Result<IacResult, String> {
+        executor::run(async move { iac::generate_iac(desc, provider.into(), cloud.into()).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_interview_questions(
+        jd: String,
+        level: Seniority,
+        n: u8,
+    ) -> Result<Vec<InterviewQuestion>, String> {
+        executor::run(
+            async move { interview::generate_interview_questions(jd, level.into(), n).await },
+        )
+        .map(|questions| questions.into_iter().map(Into::into).collect())
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn request_hash(request: String) -> Result<String, String> {
+        request_hash::prompt_hash(request).map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_onboarding(role: String, team: String, days: u8) -> Result<OnboardingPlan, String> {
+        executor::run(async move { onboarding::generate_onboarding(role, team, days).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn estimate_cost(prompt: String, model: String) -> CostEstimate {
+        cost::estimate_request_cost(&prompt, None, &model).into()
+    }
+
+    fn prompt_with_budget(prompt: String, budget: f32) -> Result<BudgetedResponse, String> {
+        executor::run(async move { budget::prompt_with_budget(prompt, budget).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn chunk_and_embed(doc: String, chunk_size: u32) -> Result<Vec<EmbeddedChunk>, String> {
+        // The WIT signature has no overlap parameter; overlap chunks by 10%.
+        let overlap = chunk_size / 10;
+        executor::run(async move { embeddings::chunk_and_embed(doc, chunk_size, overlap).await })
+            .map(|chunks| chunks.into_iter().map(Into::into).collect())
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn rerank(query: String, docs: Vec<String>, k: u32) -> Result<Vec<RerankResult>, String> {
+        executor::run(async move { rerank::rerank(query, docs, k as usize).await })
+            .map(|results| results.into_iter().map(Into::into).collect())
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_training_data(
+        task: String,
+        n: u32,
+        format: TrainingFormat,
+    ) -> Result<Vec<String>, String> {
+        executor::run(async move {
+            let pairs = training_data::generate_training_pairs(task.clone(), n).await?;
+            Ok::<_, anyhow::Error>(
+                pairs
+                    .iter()
+                    .map(|pair| training_data::serialize(pair, format.into(), &task))
+                    .collect(),
+            )
+        })
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn validate_api_response(
+        spec: String,
+        path: String,
+        status: u16,
+        body: String,
+    ) -> Result<Vec<ValidationIssue>, String> {
+        openapi::validate_response_against_spec(spec, path, status, body)
+            .map(|issues| issues.into_iter().map(Into::into).collect())
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn prompt_think(prompt: String) -> Result<ThinkingResponse, String> {
+        executor::run(async move { thinking::prompt_with_thinking(prompt).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn judge_debate(topic: String, a: String, b: String) -> Result<DebateJudgment, String> {
+        executor::run(async move { debate::judge_debate(topic, a, b).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_ab_variants(
+        copy: String,
+        hypothesis: String,
+        n: u8,
+    ) -> Result<Vec<AbVariant>, String> {
+        executor::run(async move { ab_test::generate_ab_variants(copy, hypothesis, n).await })
+            .map(|variants| variants.into_iter().map(Into::into).collect())
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn localize(keys_json: String, locales: Vec<String>) -> Result<String, String> {
+        executor::run(async move {
+            let keys: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&keys_json)
+                .map_err(|e| anyhow!("keys-json must be an object of key to source string: {e}"))?;
+            let strings = keys
+                .into_iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(text) => Ok((key, text)),
+                    _ => Err(anyhow!("value for key {key:?} is not a string")),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let translated = localize::localize(strings, locales).await?;
+            Ok::<_, anyhow::Error>(serde_json::to_string(&translated)?)
+        })
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn score_answer(
+        question: String,
+        answer: String,
+        rubric: Vec<String>,
+    ) -> Result<AnswerScore, String> {
+        executor::run(async move {
+            answer_score::score_technical_answer(question, answer, rubric).await
+        })
+        .map(Into::into)
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_wit(rust_types: String) -> Result<String, String> {
+        executor::run(async move { wit_gen::generate_wit_interface(rust_types).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn parse_table(text: String) -> Result<TableData, String> {
+        executor::run(async move { table::parse_table(text).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn prompt_with_receipt(prompt: String) -> Result<ReceiptedResponse, String> {
+        executor::run(async move { receipt::prompt_with_receipt(prompt).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn verify_receipt(receipt: String, signature: String) -> bool {
+        receipt::verify_receipt(&receipt, &signature).unwrap_or_else(|e| {
+            eprintln!("[COMPONENT] Cannot verify receipt: {e}");
+            false
+        })
+    }
+
+    fn compare_models(prompt: String, models: Vec<String>) -> Result<Vec<ModelResponse>, String> {
+        executor::run(async move { compare::compare_models(prompt, models).await })
+            .map(|responses| responses.into_iter().map(Into::into).collect())
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn analyze_grid(images: Vec<Vec<u8>>, question: String) -> Result<String, String> {
+        executor::run(async move { image_grid::analyze_image_grid(images, question).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn watermark_response(text: String, key: Vec<u8>) -> String {
+        watermark::watermark_response(&text, &key)
+    }
+
+    fn verify_watermark(text: String, key: Vec<u8>) -> bool {
+        watermark::verify_watermark(&text, &key)
+    }
+
+    fn classify_authorship(text: String) -> Result<AuthorshipResult, String> {
+        executor::run(async move { authorship::classify_authorship(text).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn interactive_refine(prompt: String, feedback: Vec<String>) -> Result<Vec<String>, String> {
+        executor::run(async move { refine::interactive_refine(prompt, feedback).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn capabilities() -> Result<CapabilityReport, String> {
+        capabilities::capabilities()
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn generate_system_prompt(
+        role: String,
+        constraints: Vec<String>,
+        examples: Vec<(String, String)>,
+    ) -> Result<String, String> {
+        executor::run(async move {
+            system_prompt::generate_system_prompt(role, constraints, examples).await
+        })
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    fn trace_request(request: String) -> TraceReport {
+        executor::run(async move {
+            let mut trace = Trace::default();
+            let result = handle_prompt(request, &mut trace).await;
+            trace.finish(result)
+        })
+        .into()
+    }
+
+    fn prompt_with_locale(prompt: String, locale: String) -> Result<LocalizedResponse, String> {
+        executor::run(async move { locale_format::prompt_with_locale(prompt, locale).await })
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn prompt_stream(prompt: String) -> Result<Vec<String>, String> {
+        executor::run(async move { streaming::prompt_stream(prompt).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn prompt_handle_with_model(prompt: String, model: String) -> String {
+        if model.trim().is_empty() {
+            return "Error: model must not be empty".to_string();
+        }
+        executor::run(async move {
+            let overrides = Overrides {
+                model: Some(model.trim().to_string()),
+                ..Overrides::default()
+            };
+            handle_request(prompt, overrides, &mut Trace::default())
+                .await
+                .0
+        })
+    }
+
+    fn chat_prompt_handle(prompt: String) -> String {
+        executor::run(async move {
+            let overrides = Overrides {
+                endpoint: Some(Endpoint::ChatCompletions),
+                ..Overrides::default()
+            };
+            handle_request(prompt, overrides, &mut Trace::default())
+                .await
+                .0
+        })
+    }
+
+    fn prompt_handle_with_system(system: String, prompt: String) -> String {
+        executor::run(async move {
+            let overrides = Overrides {
+                instructions: Some(system).filter(|system| !system.trim().is_empty()),
+                ..Overrides::default()
+            };
+            handle_request(prompt, overrides, &mut Trace::default())
+                .await
+                .0
+        })
+    }
+
+    fn embed(input: String, options: EmbeddingOptions) -> Result<Vec<f32>, String> {
+        executor::run(async move { embeddings::embed_with_options(&input, &options.into()).await })
+            .map_err(|e| format!("{e:#}"))
+    }
+
+    fn similarity(a: Vec<f32>, b: Vec<f32>) -> Result<f32, String> {
+        embeddings::cosine_similarity(&a, &b).map_err(|e| format!("{e:#}"))
+    }
+
+    fn prompt_handle_with_options(prompt: String, options: PromptOptions) -> String {
+        executor::run(async move {
+            let overrides = Overrides {
+                model: options.model,
+                temperature: options.temperature,
+                top_p: options.top_p,
+                max_output_tokens: options.max_tokens,
+                stop: options.stop,
+                max_cost_usd: options.max_cost_usd,
+                ..Overrides::default()
+            };
+            handle_request(prompt, overrides, &mut Trace::default())
+                .await
+                .0
+        })
+    }
+
+    fn prompt_handle_with_usage(prompt: String) -> (String, u32, u32) {
+        executor::run(async move {
+            let (text, usage) =
+                handle_request(prompt, Overrides::default(), &mut Trace::default()).await;
+            let usage = usage.unwrap_or_default();
+            (text, usage.input_tokens, usage.output_tokens)
+        })
+    }
+
+    fn prompt_handle_result(prompt: String) -> Result<HandledResponse, OpenaiError> {
+        executor::run(async move { try_handle_prompt(prompt, &mut Trace::default(), false).await })
+            .map(Into::into)
+            .map_err(|e| (&e).into())
+    }
+
+    fn explain_error(code: String) -> Result<ExplanationRecord, String> {
+        explain::explain_error(&code)
+            .map(Into::into)
+            .map_err(|e| format!("{e:#}"))
     }
 }
 
 bindings::export!(Component with_types_in bindings);
 
-async fn handle_request(prompt: String) -> String {
+/// Model used for every request sent by this component, unless
+/// `OPENAI_MODEL` overrides it for `prompt-handle`.
+const MODEL: &str = "gpt-4.1";
+
+/// The model `prompt-handle` sends: `OPENAI_MODEL` if set, else [`MODEL`].
+/// A blank `OPENAI_MODEL` is an error rather than a silent fallback.
+fn configured_model() -> Result<String> {
+    match std::env::var("OPENAI_MODEL") {
+        Err(_) => Ok(MODEL.to_string()),
+        Ok(model) if model.trim().is_empty() => {
+            bail!("OPENAI_MODEL is set but blank; set a model name or unset it to use {MODEL}")
+        }
+        Ok(model) => Ok(model.trim().to_string()),
+    }
+}
+
+/// System instructions from `OPENAI_INSTRUCTIONS`, if set and not blank.
+fn configured_instructions() -> Option<String> {
+    std::env::var("OPENAI_INSTRUCTIONS")
+        .ok()
+        .filter(|instructions| !instructions.trim().is_empty())
+}
+
+/// Cuts `text` to at most `max_chars` characters for a log line.
+fn truncate_for_log(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}... ({} bytes)", &text[..end], text.len()),
+        None => text.to_string(),
+    }
+}
+
+/// API that `prompt-handle` sends prompts to, from `OPENAI_API_ENDPOINT`:
+/// `responses` (the default) or `chat-completions`.
+fn configured_endpoint() -> Result<Endpoint> {
+    match std::env::var("OPENAI_API_ENDPOINT")
+        .as_deref()
+        .map(str::trim)
+    {
+        Err(_) | Ok("responses") => Ok(Endpoint::Responses),
+        Ok("chat-completions") => Ok(Endpoint::ChatCompletions),
+        Ok(other) => {
+            bail!("unknown OPENAI_API_ENDPOINT {other:?}, expected responses or chat-completions")
+        }
+    }
+}
+
+/// Values the API accepts for `temperature`.
+const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=2.0;
+/// Values the API accepts for `top_p`.
+const TOP_P_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+
+/// Sampling parameter `name` (`OPENAI_TEMPERATURE` or `OPENAI_TOP_P`), if
+/// set. Out-of-range values are rejected here rather than by the API.
+fn configured_sampling(name: &str, range: RangeInclusive<f32>) -> Result<Option<f32>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    let parsed: f32 = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("{name} must be a number, got {value:?}"))?;
+    check_range(name, parsed, range).map(Some)
+}
+
+fn check_range(name: &str, value: f32, range: RangeInclusive<f32>) -> Result<f32> {
+    if !range.contains(&value) {
+        bail!(
+            "{name} must be between {} and {}, got {value}",
+            range.start(),
+            range.end()
+        );
+    }
+    Ok(value)
+}
+
+/// Sampling parameter from a per-call override, else from `env`.
+fn sampling(
+    name: &str,
+    value: Option<f32>,
+    env: &str,
+    range: RangeInclusive<f32>,
+) -> Result<Option<f32>> {
+    match value {
+        Some(value) => check_range(name, value, range).map(Some),
+        None => configured_sampling(env, range),
+    }
+}
+
+/// Cap on generated tokens from `OPENAI_MAX_OUTPUT_TOKENS`, if set.
+fn configured_max_output_tokens() -> Result<Option<u32>> {
+    let Ok(value) = std::env::var("OPENAI_MAX_OUTPUT_TOKENS") else {
+        return Ok(None);
+    };
+    match value.trim().parse::<u32>() {
+        Ok(max) if max > 0 => Ok(Some(max)),
+        _ => bail!("OPENAI_MAX_OUTPUT_TOKENS must be a positive integer, got {value:?}"),
+    }
+}
+
+/// Default for `OPENAI_TRUNCATION_MARKER`.
+const DEFAULT_TRUNCATION_MARKER: &str = " [truncated]";
+
+/// Appends `OPENAI_TRUNCATION_MARKER` to `text` when the model stopped at
+/// the output token limit, so callers can tell a capped answer from a
+/// complete one. An empty marker only logs the truncation.
+fn mark_truncation(endpoint: Endpoint, raw_response: &str, mut text: String) -> String {
+    let Ok(json) = serde_json::from_str::<Value>(raw_response) else {
+        return text;
+    };
+    let truncated = match endpoint {
+        Endpoint::ChatCompletions => {
+            json.pointer("/choices/0/finish_reason") == Some(&"length".into())
+        }
+        _ => {
+            json["status"] == "incomplete"
+                && json.pointer("/incomplete_details/reason") == Some(&"max_output_tokens".into())
+        }
+    };
+    if truncated {
+        eprintln!("[COMPONENT] Response truncated at the output token limit");
+        text.push_str(&truncation_marker());
+    }
+    text
+}
+
+/// `OPENAI_TRUNCATION_MARKER`, or [`DEFAULT_TRUNCATION_MARKER`] when unset.
+fn truncation_marker() -> String {
+    std::env::var("OPENAI_TRUNCATION_MARKER")
+        .unwrap_or_else(|_| DEFAULT_TRUNCATION_MARKER.to_string())
+}
+
+/// Most stop sequences the API accepts.
+const MAX_STOP_SEQUENCES: usize = 4;
+
+/// Stop sequences from `OPENAI_STOP_SEQUENCES`, a JSON array of strings
+/// (so sequences may contain commas, quotes, and newlines).
+fn configured_stop() -> Result<Vec<String>> {
+    let Ok(value) = std::env::var("OPENAI_STOP_SEQUENCES") else {
+        return Ok(Vec::new());
+    };
+    let stop: Vec<String> = serde_json::from_str(&value).map_err(|e| {
+        anyhow!("OPENAI_STOP_SEQUENCES must be a JSON array of strings, such as [\"###\"]: {e}")
+    })?;
+    check_stop("OPENAI_STOP_SEQUENCES", stop)
+}
+
+fn check_stop(name: &str, stop: Vec<String>) -> Result<Vec<String>> {
+    if stop.len() > MAX_STOP_SEQUENCES {
+        bail!(
+            "{name} allows at most {MAX_STOP_SEQUENCES} stop sequences, got {}",
+            stop.len()
+        );
+    }
+    if stop.iter().any(String::is_empty) {
+        bail!("{name} must not contain empty stop sequences");
+    }
+    Ok(stop)
+}
+
+/// Cuts `text` before the earliest of the `stop` sequences, which is left
+/// out as the API does.
+fn cut_at_stop(mut text: String, stop: &[String]) -> String {
+    if let Some(cut) = stop
+        .iter()
+        .filter_map(|sequence| text.find(sequence.as_str()))
+        .min()
+    {
+        text.truncate(cut);
+    }
+    text
+}
+
+/// Per-call replacements for the environment configuration of
+/// `prompt-handle`. Unset fields use the configured value.
+#[derive(Debug, Default)]
+struct Overrides {
+    model: Option<String>,
+    endpoint: Option<Endpoint>,
+    instructions: Option<String>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
+    stop: Option<Vec<String>>,
+    /// Cost ceiling across every upstream call of this request.
+    max_cost_usd: Option<f64>,
+    /// Tenant whose policies apply to the answer; not sent upstream.
+    tenant: Option<String>,
+}
+
+/// Fields of [`StructuredPrompt`] besides `input`, reported by `capabilities`.
+const REQUEST_OPTIONS: &[&str] = &[
+    "model",
+    "instructions",
+    "temperature",
+    "top_p",
+    "max_output_tokens",
+    "stop",
+    "max_cost_usd",
+    "tenant",
+];
+
+/// A `prompt-handle` input given as a JSON object instead of plain text.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructuredPrompt {
+    input: String,
+    model: Option<String>,
+    instructions: Option<String>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
+    stop: Option<Vec<String>>,
+    max_cost_usd: Option<f64>,
+    tenant: Option<String>,
+}
+
+/// Reads per-request options from `prompt` when it is a JSON object with a
+/// string `input`; any other text is the prompt itself.
+fn parse_structured_prompt(prompt: String) -> Result<(String, Overrides)> {
+    let is_structured = prompt.trim_start().starts_with('{')
+        && serde_json::from_str::<Value>(&prompt)
+            .is_ok_and(|value| value.get("input").is_some_and(Value::is_string));
+    if !is_structured {
+        return Ok((prompt, Overrides::default()));
+    }
+
+    let structured: StructuredPrompt =
+        serde_json::from_str(&prompt).map_err(|e| anyhow!("Invalid structured prompt: {e}"))?;
+    let overrides = Overrides {
+        model: structured.model,
+        instructions: structured
+            .instructions
+            .filter(|instructions| !instructions.trim().is_empty()),
+        temperature: structured.temperature,
+        top_p: structured.top_p,
+        max_output_tokens: structured.max_output_tokens,
+        stop: structured.stop,
+        max_cost_usd: structured.max_cost_usd,
+        tenant: structured.tenant.filter(|tenant| !tenant.trim().is_empty()),
+        ..Overrides::default()
+    };
+    Ok((structured.input, overrides))
+}
+
+/// Resolves `overrides` against the environment configuration into the
+/// endpoint and request `prompt-handle` sends for `prompt`, before
+/// middleware runs.
+fn configure_request(prompt: String, overrides: Overrides) -> Result<(Endpoint, PromptRequest)> {
+    let model = match overrides.model {
+        Some(model) if model.trim().is_empty() => bail!("model must not be empty"),
+        Some(model) => model.trim().to_string(),
+        None => configured_model()?,
+    };
+    let max_output_tokens = match overrides.max_output_tokens {
+        Some(0) => bail!("max-tokens must be positive"),
+        Some(max) => Some(max),
+        None => configured_max_output_tokens()?,
+    };
+    let stop = match overrides.stop {
+        Some(stop) => check_stop("stop", stop)?,
+        None => configured_stop()?,
+    };
+    let endpoint = overrides.endpoint.map_or_else(configured_endpoint, Ok)?;
+    let request = PromptRequest {
+        model,
+        input: prompt,
+        instructions: overrides.instructions.or_else(configured_instructions),
+        temperature: sampling(
+            "temperature",
+            overrides.temperature,
+            "OPENAI_TEMPERATURE",
+            TEMPERATURE_RANGE,
+        )?,
+        top_p: sampling("top-p", overrides.top_p, "OPENAI_TOP_P", TOP_P_RANGE)?,
+        max_output_tokens,
+        stop,
+    };
+    Ok((endpoint, request))
+}
+
+/// Runs `prompt-handle`, accepting either plain text or a structured prompt.
+async fn handle_prompt(prompt: String, trace: &mut Trace) -> String {
+    try_handle_prompt(prompt, trace, true)
+        .await
+        .map_or_else(|e| format!("Error: {}", e), |answer| answer.text)
+}
+
+/// [`handle_prompt`] with failures returned as errors; see
+/// [`try_handle_request`] for `raw_fallback`.
+async fn try_handle_prompt(
+    prompt: String,
+    trace: &mut Trace,
+    raw_fallback: bool,
+) -> Result<Answer> {
+    let started = Trace::start();
+    match parse_structured_prompt(prompt) {
+        Ok((prompt, overrides)) => try_handle_request(prompt, overrides, trace, raw_fallback).await,
+        Err(e) => {
+            eprintln!("[COMPONENT] Configuration error: {e}");
+            trace.record("request-middleware", started, false, e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Runs the `prompt-handle` pipeline with `overrides` applied, returning
+/// the answer, or failures as `Error: ...` text, and the usage the response
+/// reported.
+async fn handle_request(
+    prompt: String,
+    overrides: Overrides,
+    trace: &mut Trace,
+) -> (String, Option<Usage>) {
+    try_handle_request(prompt, overrides, trace, true)
+        .await
+        .map_or_else(
+            |e| {
+                eprintln!(
+                    "[COMPONENT] Request failed with code {}",
+                    explain::ErrorCode::of(&e).explanation().code
+                );
+                (format!("Error: {}", e), None)
+            },
+            |answer| (answer.text, answer.usage),
+        )
+}
+
+/// What the `prompt-handle` pipeline produced for one request.
+#[derive(Debug)]
+struct Answer {
+    text: String,
+    /// Model the request was sent to, after middleware.
+    model: String,
+    /// [`request_hash::hash_request`] of the request as sent.
+    request_hash: String,
+    /// Token usage reported by the response, if it had any.
+    usage: Option<Usage>,
+    /// Policies that flagged the answer without blocking it.
+    flags: Vec<PolicyReport>,
+    /// Whether `text` is the soft-fail fallback rather than a model answer.
+    degraded: bool,
+}
+
+impl From<Answer> for HandledResponse {
+    fn from(answer: Answer) -> Self {
+        Self {
+            text: answer.text,
+            flagged_by: answer.flags.into_iter().map(Into::into).collect(),
+            degraded: answer.degraded,
+        }
+    }
+}
+
+/// Runs the `prompt-handle` pipeline with `overrides` applied. With
+/// `raw_fallback`, a response whose answer cannot be extracted is returned
+/// as raw JSON instead of failing.
+async fn try_handle_request(
+    prompt: String,
+    overrides: Overrides,
+    trace: &mut Trace,
+    raw_fallback: bool,
+) -> Result<Answer> {
     eprintln!("[COMPONENT] Received prompt: {}", prompt);
 
-    match openai_proxy(prompt).await {
-        Ok(response) => {
-            eprintln!("[COMPONENT] Got response from OpenAI API");
+    let tenant = overrides.tenant.clone();
+    let started = Trace::start();
+    let configured = (|| -> Result<_> {
+        if let Some(ceiling) = overrides.max_cost_usd {
+            request_budget::set_ceiling(ceiling)?;
+        }
+        let soft_fail = soft_fail::configured(|name| std::env::var(name).ok())?;
+        let (endpoint, request) = configure_request(prompt, overrides)?;
+        Ok((endpoint, request, soft_fail))
+    })();
+    let (endpoint, mut request, soft_fail) = match configured {
+        Ok(configured) => configured,
+        Err(e) => {
+            eprintln!("[COMPONENT] Configuration error: {e}");
+            trace.record("request-middleware", started, false, e.to_string());
+            return Err(e);
+        }
+    };
+    eprintln!(
+        "[COMPONENT] Using model {} via {}",
+        request.model,
+        endpoint.path()
+    );
+    if let Some(instructions) = &request.instructions {
+        eprintln!(
+            "[COMPONENT] Using instructions: {}",
+            truncate_for_log(instructions, 80)
+        );
+    }
 
-            let mut stream =
-                executor::incoming_body(response.consume().expect("response should be consumable"));
-            let mut collected_data = Vec::new();
-
-            // Collect complete non-streaming response
-            while let Some(chunk) = stream.next().await {
-                match chunk {
-                    Ok(data) => collected_data.extend_from_slice(&data),
-                    Err(e) => {
-                        eprintln!("[COMPONENT] Error receiving body: {e}");
-                        return format!("Error collecting response: {}", e);
-                    }
-                }
-            }
+    let request_hash = match middleware::install_from_env()
+        .and_then(|()| middleware::apply_request(&mut request))
+        .and_then(|()| request_hash::hash_request(endpoint, &request))
+    {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("[COMPONENT] Request middleware error: {e}");
+            trace.record("request-middleware", started, false, e.to_string());
+            return Err(e);
+        }
+    };
+    trace.record(
+        "request-middleware",
+        started,
+        true,
+        format!(
+            "model {}, {} input bytes",
+            request.model,
+            request.input.len()
+        ),
+    );
+    eprintln!("[COMPONENT] Request hash {request_hash}");
 
-            eprintln!(
-                "[COMPONENT] Response collected, {} bytes",
-                collected_data.len()
-            );
+    // Collect complete non-streaming response
+    let started = Trace::start();
+    let response = match request_body(endpoint, &request) {
+        Ok(body) => fetch(endpoint, body).await,
+        Err(e) => Err(e),
+    };
+    let collected_data = match response {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("[COMPONENT] OpenAI request error: {e}");
+            trace.record("transport", started, false, e.to_string());
+            return match soft_fail::fallback_for(&e, soft_fail.as_deref()) {
+                Some(fallback) => Ok(Answer {
+                    text: fallback,
+                    model: request.model,
+                    request_hash,
+                    usage: None,
+                    flags: Vec::new(),
+                    degraded: true,
+                }),
+                None => Err(e),
+            };
+        }
+    };
 
-            // Convert to string
-            let raw_response = match String::from_utf8(collected_data) {
-                Ok(text) => text,
-                Err(e) => {
-                    eprintln!("[COMPONENT] UTF-8 error: {e}");
-                    return format!("Error: Invalid UTF-8 response");
-                }
+    eprintln!(
+        "[COMPONENT] Response collected, {} bytes",
+        collected_data.len()
+    );
+    trace.record(
+        "transport",
+        started,
+        true,
+        format!("{} response bytes", collected_data.len()),
+    );
+
+    // Convert to string
+    let started = Trace::start();
+    let raw_response = match String::from_utf8(collected_data) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("[COMPONENT] UTF-8 error: {e}");
+            trace.record("decode", started, false, e.to_string());
+            return Err(OpenAiError::ParseError("Invalid UTF-8 response".to_string()).into());
+        }
+    };
+    trace.record("decode", started, true, "valid UTF-8");
+
+    // Parse JSON and extract output text for non-streaming response
+    let started = Trace::start();
+    let parsed = match endpoint {
+        Endpoint::ChatCompletions => parse_chat_completions_response(&raw_response),
+        _ => parse_complete_response(&raw_response),
+    };
+    let mut usage = None;
+    let mut text = match parsed {
+        Ok(ParsedResponse {
+            text,
+            usage: reported,
+        }) => {
+            usage = reported;
+            trace.record(
+                "parse",
+                started,
+                true,
+                format!("{} answer bytes", text.len()),
+            );
+            // The Responses API has no `stop` parameter, so stop sequences
+            // are applied to its answer here.
+            let text = match endpoint {
+                Endpoint::Responses => cut_at_stop(text, &request.stop),
+                _ => text,
             };
+            mark_truncation(endpoint, &raw_response, text)
+        }
+        Err(e) if !raw_fallback => {
+            eprintln!("[COMPONENT] JSON parse error: {e}");
+            trace.record("parse", started, false, e.to_string());
+            return Err(e);
+        }
+        Err(e) => {
+            eprintln!("[COMPONENT] JSON parse error: {e}");
+            trace.record(
+                "parse",
+                started,
+                false,
+                format!("{e}; returning the raw response"),
+            );
+            raw_response.clone() // Fallback to raw JSON
+        }
+    };
 
-            // Parse JSON and extract output text for non-streaming response
-            match parse_complete_response(&raw_response) {
-                Ok(text) => text,
-                Err(e) => {
-                    eprintln!("[COMPONENT] JSON parse error: {e}");
-                    raw_response // Fallback to raw JSON
-                }
-            }
+    let mut flags = Vec::new();
+    let started = Trace::start();
+    match content_filter::apply(&raw_response, tenant.as_deref()) {
+        Ok(flag) => {
+            let detail = flag.as_ref().map_or(String::new(), |report| {
+                format!("flagged: {}", report.rules.join(", "))
+            });
+            trace.record("content-filter", started, true, detail);
+            flags.extend(flag);
         }
         Err(e) => {
-            eprintln!("[COMPONENT] OpenAI request error: {e}");
-            format!("Error: {}", e)
+            trace.record("content-filter", started, false, e.to_string());
+            return Err(e);
+        }
+    }
+
+    let started = Trace::start();
+    match guard::check(&text).await {
+        Ok(flag) => {
+            let detail = flag.as_ref().map_or(String::new(), |report| {
+                format!("flagged: {}", report.rules.join(", "))
+            });
+            trace.record("guard", started, true, detail);
+            flags.extend(flag);
+        }
+        Err(e) => {
+            trace.record("guard", started, false, e.to_string());
+            return Err(e);
+        }
+    }
+
+    let started = Trace::start();
+    match middleware::apply_response(&mut text) {
+        Ok(()) => {
+            trace.record("response-middleware", started, true, "");
+            Ok(Answer {
+                text,
+                model: request.model,
+                request_hash,
+                usage,
+                flags,
+                degraded: false,
+            })
+        }
+        Err(e) => {
+            eprintln!("[COMPONENT] Response middleware error: {e}");
+            trace.record("response-middleware", started, false, e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Body of a Responses API request. Optional fields are left out of the
+/// JSON when unset.
+#[derive(Debug, Serialize)]
+struct RequestBody<'a> {
+    model: &'a str,
+    input: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    stream: bool,
+}
+
+impl<'a> RequestBody<'a> {
+    fn new(request: &'a PromptRequest) -> Self {
+        Self {
+            model: &request.model,
+            input: &request.input,
+            instructions: request
+                .instructions
+                .as_deref()
+                .filter(|instructions| !instructions.trim().is_empty()),
+            max_output_tokens: request.max_output_tokens,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stream: false,
+        }
+    }
+}
+
+/// One message of a Chat Completions request.
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+/// Body of a Chat Completions request.
+#[derive(Debug, Serialize)]
+struct ChatRequestBody<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    /// Chat Completions' name for `max_output_tokens`, as understood by
+    /// OpenAI-compatible servers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    stop: &'a [String],
+    stream: bool,
+}
+
+impl<'a> ChatRequestBody<'a> {
+    /// The request for `/v1/chat/completions`, the API exposed by most
+    /// OpenAI-compatible servers. Instructions become a leading system message.
+    fn new(request: &'a PromptRequest) -> Self {
+        let system = request
+            .instructions
+            .as_deref()
+            .filter(|instructions| !instructions.trim().is_empty())
+            .map(|content| ChatMessage {
+                role: "system",
+                content,
+            });
+        Self {
+            model: &request.model,
+            messages: system
+                .into_iter()
+                .chain([ChatMessage {
+                    role: "user",
+                    content: &request.input,
+                }])
+                .collect(),
+            max_tokens: request.max_output_tokens,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop: &request.stop,
+            stream: false,
+        }
+    }
+}
+
+/// The JSON body `prompt-handle` sends to `endpoint` for `request`.
+fn request_body(endpoint: Endpoint, request: &PromptRequest) -> Result<Vec<u8>> {
+    Ok(match endpoint {
+        Endpoint::ChatCompletions => serde_json::to_vec(&ChatRequestBody::new(request))?,
+        _ => serde_json::to_vec(&RequestBody::new(request))?,
+    })
+}
+
+/// OpenAI API endpoints the component calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    Responses,
+    ChatCompletions,
+    Embeddings,
+}
+
+impl Endpoint {
+    fn path(self) -> &'static str {
+        match self {
+            Self::Responses => "/v1/responses",
+            Self::ChatCompletions => "/v1/chat/completions",
+            Self::Embeddings => "/v1/embeddings",
+        }
+    }
+}
+
+/// Sends a request body for `endpoint` to the configured provider and
+/// returns the complete response body.
+///
+/// `AI_PROVIDER` selects the provider: `openai` (the default) or `mock`,
+/// which answers locally without any network call (see [`mock`]). Chaos
+/// faults (see [`chaos`]) are injected here, so they fail callers exactly as
+/// provider failures do. Every call is first admitted against the request's
+/// cost ceiling (see [`request_budget`]).
+async fn fetch(endpoint: Endpoint, json_body: Vec<u8>) -> Result<Vec<u8>> {
+    let worst_case_usd = request_budget::admit(endpoint, &json_body)?;
+    let body = fetch_admitted(endpoint, json_body).await;
+    request_budget::settle(endpoint, worst_case_usd, body.as_deref().ok());
+    body
+}
+
+/// Like [`fetch`], for a streaming request: body chunks are passed to
+/// `consume` as they arrive, then `None` at the end of the body. `consume`
+/// stops the stream early with `Break`, carrying the final response object
+/// if the stream reported one; the call is settled from its `usage`, or at
+/// its worst case without it.
+async fn fetch_stream(
+    endpoint: Endpoint,
+    json_body: Vec<u8>,
+    mut consume: impl FnMut(Option<&[u8]>) -> Result<ControlFlow<Option<Vec<u8>>>>,
+) -> Result<()> {
+    let worst_case_usd = request_budget::admit(endpoint, &json_body)?;
+    let response = stream_admitted(endpoint, json_body, &mut consume).await;
+    // A stream without a final response is charged as one without usage.
+    let reported = response
+        .as_ref()
+        .map(|response| response.as_deref().unwrap_or_default());
+    request_budget::settle(endpoint, worst_case_usd, reported.ok());
+    response.map(drop)
+}
+
+async fn stream_admitted(
+    endpoint: Endpoint,
+    json_body: Vec<u8>,
+    consume: &mut impl FnMut(Option<&[u8]>) -> Result<ControlFlow<Option<Vec<u8>>>>,
+) -> Result<Option<Vec<u8>>> {
+    let fault = chaos::choose_fault()?;
+    if let Some(fault) = fault {
+        chaos::fail_before_send(fault)?;
+    }
+
+    let mut chunks = match std::env::var("AI_PROVIDER").as_deref() {
+        Err(_) | Ok("openai") => {
+            let response = send_request(endpoint, json_body).await?;
+            eprintln!("[COMPONENT] Got streaming response from OpenAI API");
+            let body = response
+                .consume()
+                .map_err(|()| anyhow!("response body already consumed"))?;
+            executor::incoming_body(body)
+                .map(|chunk| {
+                    chunk.map_err(|e| {
+                        OpenAiError::IoError(format!("failed to read response body: {e}")).into()
+                    })
+                })
+                .boxed_local()
+        }
+        Ok("mock") => futures::stream::once(futures::future::ready(Ok::<_, anyhow::Error>(
+            mock::respond(endpoint, &json_body).await?,
+        )))
+        .boxed_local(),
+        Ok(other) => bail!("unsupported AI_PROVIDER {other:?}, expected `openai` or `mock`"),
+    };
+
+    while let Some(chunk) = chunks.next().await {
+        let chunk = match fault {
+            Some(fault) => chaos::corrupt_body(fault, chunk?),
+            None => chunk?,
+        };
+        if let ControlFlow::Break(response) = consume(Some(&chunk))? {
+            return Ok(response);
+        }
+        // A truncated stream ends after half of its first chunk.
+        if fault.is_some() {
+            break;
+        }
+    }
+    Ok(match consume(None)? {
+        ControlFlow::Break(response) => response,
+        ControlFlow::Continue(()) => None,
+    })
+}
+
+async fn fetch_admitted(endpoint: Endpoint, json_body: Vec<u8>) -> Result<Vec<u8>> {
+    let fault = chaos::choose_fault()?;
+    if let Some(fault) = fault {
+        chaos::fail_before_send(fault)?;
+    }
+
+    let body = match std::env::var("AI_PROVIDER").as_deref() {
+        Err(_) | Ok("openai") => {
+            let response = send_request(endpoint, json_body).await?;
+            eprintln!("[COMPONENT] Got response from OpenAI API");
+            collect_body(response).await?
         }
+        Ok("mock") => mock::respond(endpoint, &json_body).await?,
+        Ok(other) => bail!("unsupported AI_PROVIDER {other:?}, expected `openai` or `mock`"),
+    };
+
+    Ok(match fault {
+        Some(fault) => chaos::corrupt_body(fault, body),
+        None => body,
+    })
+}
+
+/// Default for `OPENAI_BASE_URL`.
+const DEFAULT_BASE_URL: &str = "https://api.openai.com";
+
+/// Scheme, host, port, and optional path prefix that endpoint paths are
+/// appended to: `OPENAI_BASE_URL` if set, else the public OpenAI API.
+fn base_url() -> Result<Url> {
+    parse_base_url(
+        &std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
+    )
+}
+
+fn parse_base_url(value: &str) -> Result<Url> {
+    let url = Url::parse(value.trim()).map_err(|e| {
+        anyhow!(
+            "OPENAI_BASE_URL {value:?} is not a valid URL ({e}); \
+             expected e.g. https://api.openai.com or http://localhost:8080"
+        )
+    })?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!(
+            "OPENAI_BASE_URL must use http or https, got {:?}",
+            url.scheme()
+        );
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        bail!("OPENAI_BASE_URL {value:?} has no host");
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        bail!("OPENAI_BASE_URL {value:?} must not contain a query or fragment");
     }
+    if !url.username().is_empty() || url.password().is_some() {
+        bail!("OPENAI_BASE_URL must not contain credentials; use OPENAI_API_KEY");
+    }
+
+    Ok(url)
 }
 
-async fn openai_proxy(prompt: String) -> Result<IncomingResponse> {
-    let base = "https://api.openai.com/v1/responses";
+/// POSTs a JSON body to an OpenAI endpoint and returns the response once a
+/// successful status has been received.
+async fn send_request(endpoint: Endpoint, json_body: Vec<u8>) -> Result<IncomingResponse> {
+    let base = base_url()?;
 
-    let api_key = std::env::var("OPENAI_API_KEY")
-        .map_err(|_| anyhow!("OPENAI_API_KEY environment variable not set"))?;
+    let api_key = std::env::var("OPENAI_API_KEY").map_err(|_| OpenAiError::MissingApiKey)?;
 
-    let url: Url = Url::parse(base)?;
+    // Keep any path prefix of the base URL (e.g. a proxy mount point).
+    let mut url = base.clone();
+    url.set_path(&format!(
+        "{}{}",
+        base.path().trim_end_matches('/'),
+        endpoint.path()
+    ));
 
     // Build headers
     let headers = Fields::new();
     headers
-        .append(&"content-type".to_string(), b"application/json")
+        .append("content-type", b"application/json")
         .map_err(|_| anyhow!("failed to set content-type"))?;
     headers
-        .append(
-            &"authorization".to_string(),
-            format!("Bearer {}", api_key).as_bytes(),
-        )
+        .append("authorization", format!("Bearer {}", api_key).as_bytes())
         .map_err(|_| anyhow!("failed to set authorization"))?;
 
     let outgoing_request = OutgoingRequest::new(headers);
@@ -128,61 +1309,127 @@ async fn openai_proxy(prompt: String) -> Result<IncomingResponse> {
         )))
         .map_err(|()| anyhow!("failed to set authority"))?;
 
-    // JSON payload with stream: false for complete response
-    let json_request = format!(
-        r#"{{
-            "model": "gpt-4.1",
-            "input": "{}",
-            "stream": false
-        }}"#,
-        prompt.replace('\\', "\\\\").replace('"', "\\\"")
-    );
-
     // Send request body
     let mut body = executor::outgoing_body(outgoing_request.body().expect("body writable"));
-    body.send(json_request.into_bytes()).await?;
+    body.send(json_body)
+        .await
+        .map_err(|e| OpenAiError::IoError(format!("failed to send request body: {e}")))?;
     drop(body);
 
     // Send request
-    let response = executor::outgoing_request_send(outgoing_request).await?;
+    let response = executor::outgoing_request_send(outgoing_request)
+        .await
+        .map_err(|e| OpenAiError::IoError(format!("request to OpenAI failed: {e}")))?;
 
     let status = response.status();
     if !(200..300).contains(&status) {
-        bail!("HTTP {} from OpenAI", status);
+        let retry_after = response
+            .headers()
+            .get("retry-after")
+            .into_iter()
+            .next()
+            .map(|value| String::from_utf8_lossy(&value).into_owned());
+        // The error body explains the status; an unreadable one is left out.
+        let body = collect_body(response).await.unwrap_or_default();
+        return Err(OpenAiError::from_status(status, retry_after.as_deref(), &body).into());
     }
 
     Ok(response)
 }
 
-fn parse_complete_response(json_str: &str) -> Result<String> {
-    let json: Value =
-        serde_json::from_str(json_str).map_err(|e| anyhow!("Failed to parse JSON: {}", e))?;
-
-    // Primary path: OpenAI Responses API format from your exact log output
-    if let Some(output_array) = json.get("output") {
-        if let Some(first_msg) = output_array.get(0usize) {
-            // Use usize index for array access
-            if let Some(content_array) = first_msg.get("content") {
-                if let Some(content_item) = content_array.get(0usize) {
-                    // Use usize index
-                    // Direct "text" field check first (handles your exact format)
-                    if let Some(text) = content_item.get("text") {
-                        if let Some(text_str) = text.as_str() {
-                            return Ok(text_str.to_string());
-                        }
-                    }
-                    // Fallback to output_text.text
-                    if let Some(output_text) = content_item.get("output_text") {
-                        if let Some(text) = output_text.get("text") {
-                            if let Some(text_str) = text.as_str() {
-                                return Ok(text_str.to_string());
-                            }
-                        }
-                    }
-                }
-            }
+/// Reads an incoming response body to completion.
+async fn collect_body(response: IncomingResponse) -> Result<Vec<u8>> {
+    let mut stream =
+        executor::incoming_body(response.consume().expect("response should be consumable"));
+    let mut collected_data = Vec::new();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk
+            .map_err(|e| OpenAiError::IoError(format!("failed to read response body: {e}")))?;
+        collected_data.extend_from_slice(&chunk);
+    }
+
+    Ok(collected_data)
+}
+
+/// Token counts reported in a response's `usage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Usage {
+    input_tokens: u32,
+    output_tokens: u32,
+    total_tokens: u32,
+}
+
+impl Usage {
+    /// Reads the `usage` of a response from `endpoint`: `input_tokens` and
+    /// `output_tokens` on the Responses API, `prompt_tokens` and
+    /// `completion_tokens` on Chat Completions, and only `prompt_tokens` on
+    /// Embeddings, which bills no output.
+    fn parse(endpoint: Endpoint, json: &Value) -> Option<Self> {
+        let (input, output) = match endpoint {
+            Endpoint::Responses => ("input_tokens", Some("output_tokens")),
+            Endpoint::ChatCompletions => ("prompt_tokens", Some("completion_tokens")),
+            Endpoint::Embeddings => ("prompt_tokens", None),
+        };
+        let usage = json.get("usage")?;
+        let tokens = |field: &str| {
+            usage[field]
+                .as_u64()
+                .map(|n| u32::try_from(n).unwrap_or(u32::MAX))
+        };
+        let input_tokens = tokens(input)?;
+        let output_tokens = match output {
+            Some(output) => tokens(output)?,
+            None => 0,
+        };
+        Some(Self {
+            input_tokens,
+            output_tokens,
+            total_tokens: tokens("total_tokens")
+                .unwrap_or_else(|| input_tokens.saturating_add(output_tokens)),
+        })
+    }
+}
+
+/// Answer text and token usage extracted from a response.
+#[derive(Debug)]
+struct ParsedResponse {
+    text: String,
+    usage: Option<Usage>,
+}
+
+fn parse_complete_response(json_str: &str) -> Result<ParsedResponse> {
+    let json: Value = serde_json::from_str(json_str)
+        .map_err(|e| OpenAiError::ParseError(format!("Failed to parse JSON: {}", e)))?;
+    let usage = Usage::parse(Endpoint::Responses, &json);
+
+    // Reasoning models put `reasoning` items before the message, and a
+    // message can hold several text parts, so every `message` item's text
+    // parts are joined rather than reading `output[0].content[0]`.
+    let mut text = None::<String>;
+    let mut refusal = None;
+    let messages = json["output"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|item| item.get("type").is_none_or(|kind| kind == "message"));
+    for part in messages.flat_map(|item| item["content"].as_array().into_iter().flatten()) {
+        // Text is normally `text`; older responses nest it in `output_text`.
+        let part_text = part["text"]
+            .as_str()
+            .or_else(|| part.pointer("/output_text/text").and_then(Value::as_str));
+        if let Some(part_text) = part_text {
+            text.get_or_insert_with(String::new).push_str(part_text);
+        } else if let Some(message) = part["refusal"].as_str() {
+            refusal.get_or_insert(message);
         }
     }
+    if let Some(text) = text {
+        return Ok(ParsedResponse { text, usage });
+    }
+    if let Some(message) = refusal {
+        return Err(OpenAiError::ParseError(format!("Model refused: {message}")).into());
+    }
 
     // Debug: Log the structure if parsing fails
     eprintln!(
@@ -190,12 +1437,45 @@ fn parse_complete_response(json_str: &str) -> Result<String> {
         json.as_object().map(|o| o.keys().collect::<Vec<_>>())
     );
 
-    bail!("No output text found in response")
+    Err(OpenAiError::ParseError("No output text found in response".to_string()).into())
 }
 
-// [Keep the entire executor module unchanged - it's the same as original]
+/// Extracts `choices[0].message.content` from a Chat Completions response.
+fn parse_chat_completions_response(json_str: &str) -> Result<ParsedResponse> {
+    let json: Value = serde_json::from_str(json_str)
+        .map_err(|e| OpenAiError::ParseError(format!("Failed to parse JSON: {}", e)))?;
+
+    if let Some(content) = json
+        .pointer("/choices/0/message/content")
+        .and_then(Value::as_str)
+    {
+        return Ok(ParsedResponse {
+            text: content.to_string(),
+            usage: Usage::parse(Endpoint::ChatCompletions, &json),
+        });
+    }
+    if let Some(refusal) = json
+        .pointer("/choices/0/message/refusal")
+        .and_then(Value::as_str)
+    {
+        return Err(OpenAiError::ParseError(format!("Model refused: {refusal}")).into());
+    }
+
+    eprintln!(
+        "[COMPONENT] JSON keys: {:?}",
+        json.as_object().map(|o| o.keys().collect::<Vec<_>>())
+    );
+
+    Err(OpenAiError::ParseError("No message content found in response".to_string()).into())
+}
+
+// Minimal single-threaded executor over WASI pollables: `run` drives a future
+// to completion, `sleep` waits on the monotonic clock (mock provider latency),
+// and the remaining helpers adapt WASI HTTP requests and bodies to futures,
+// sinks, and streams.
 mod executor {
     use crate::bindings::wasi::{
+        clocks::monotonic_clock,
         http::{
             outgoing_handler,
             types::{
@@ -214,6 +1494,7 @@ mod executor {
         rc::Rc,
         sync::{Arc, Mutex},
         task::{Context, Poll, Wake, Waker},
+        time::Duration,
     };
 
     const READ_SIZE: u64 = 16 * 1024;
@@ -262,6 +1543,23 @@ mod executor {
         }
     }
 
+    pub fn sleep(duration: Duration) -> impl Future<Output = ()> {
+        let deadline = monotonic_clock::now()
+            .saturating_add(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX));
+
+        future::poll_fn(move |context| {
+            if monotonic_clock::now() >= deadline {
+                Poll::Ready(())
+            } else {
+                WAKERS.lock().unwrap().push((
+                    monotonic_clock::subscribe_instant(deadline),
+                    context.waker().clone(),
+                ));
+                Poll::Pending
+            }
+        })
+    }
+
     pub fn outgoing_body(body: OutgoingBody) -> impl Sink<Vec<u8>, Error = Error> {
         struct Outgoing(Option<(OutputStream, OutgoingBody)>);
 
@@ -432,3 +1730,208 @@ mod executor {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(input: &str) -> PromptRequest {
+        PromptRequest {
+            model: "gpt-4o-mini".to_string(),
+            input: input.to_string(),
+            instructions: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            stop: Vec::new(),
+        }
+    }
+
+    fn body(endpoint: Endpoint, request: &PromptRequest) -> Value {
+        serde_json::from_slice(&request_body(endpoint, request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn control_characters_quotes_and_backslashes_round_trip() {
+        let input = "line one\nline \"two\"\tC:\\path\r\n\u{0}\u{1f} ünïcödé 日本";
+        let responses = body(Endpoint::Responses, &request(input));
+        assert_eq!(responses["input"], input);
+        let chat = body(Endpoint::ChatCompletions, &request(input));
+        assert_eq!(chat["messages"][0]["content"], input);
+    }
+
+    #[test]
+    fn unset_options_are_left_out() {
+        let responses = body(Endpoint::Responses, &request("Hi"));
+        assert_eq!(
+            responses,
+            serde_json::json!({"model": "gpt-4o-mini", "input": "Hi", "stream": false})
+        );
+        let chat = body(Endpoint::ChatCompletions, &request("Hi"));
+        assert_eq!(
+            chat,
+            serde_json::json!({
+                "model": "gpt-4o-mini",
+                "messages": [{"role": "user", "content": "Hi"}],
+                "stream": false,
+            })
+        );
+    }
+
+    #[test]
+    fn set_options_are_sent() {
+        let request = PromptRequest {
+            instructions: Some("Answer in French.".to_string()),
+            temperature: Some(0.5),
+            top_p: Some(0.25),
+            max_output_tokens: Some(64),
+            stop: vec!["END".to_string()],
+            ..request("Hi")
+        };
+        assert_eq!(
+            body(Endpoint::Responses, &request),
+            serde_json::json!({
+                "model": "gpt-4o-mini",
+                "input": "Hi",
+                "instructions": "Answer in French.",
+                "max_output_tokens": 64,
+                "temperature": 0.5,
+                "top_p": 0.25,
+                "stream": false,
+            })
+        );
+        assert_eq!(
+            body(Endpoint::ChatCompletions, &request),
+            serde_json::json!({
+                "model": "gpt-4o-mini",
+                "messages": [
+                    {"role": "system", "content": "Answer in French."},
+                    {"role": "user", "content": "Hi"},
+                ],
+                "max_tokens": 64,
+                "temperature": 0.5,
+                "top_p": 0.25,
+                "stop": ["END"],
+                "stream": false,
+            })
+        );
+    }
+
+    #[test]
+    fn usage_is_read_in_each_endpoint_naming() {
+        let responses = serde_json::json!({"usage": {"input_tokens": 3, "output_tokens": 4}});
+        let chat = serde_json::json!({
+            "usage": {"prompt_tokens": 3, "completion_tokens": 4, "total_tokens": 8}
+        });
+        let embeddings = serde_json::json!({"usage": {"prompt_tokens": 3, "total_tokens": 3}});
+        let usage = |input_tokens, output_tokens, total_tokens| {
+            Some(Usage {
+                input_tokens,
+                output_tokens,
+                total_tokens,
+            })
+        };
+        assert_eq!(
+            Usage::parse(Endpoint::Responses, &responses),
+            usage(3, 4, 7)
+        );
+        assert_eq!(
+            Usage::parse(Endpoint::ChatCompletions, &chat),
+            usage(3, 4, 8)
+        );
+        assert_eq!(
+            Usage::parse(Endpoint::Embeddings, &embeddings),
+            usage(3, 0, 3)
+        );
+        // Each endpoint reads only its own names.
+        assert_eq!(Usage::parse(Endpoint::Responses, &chat), None);
+        assert_eq!(Usage::parse(Endpoint::ChatCompletions, &responses), None);
+        assert_eq!(
+            Usage::parse(Endpoint::Responses, &serde_json::json!({})),
+            None
+        );
+    }
+
+    #[test]
+    fn handle_request_returns_the_reported_usage() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        let (text, usage) = futures::executor::block_on(handle_request(
+            "Hello".to_string(),
+            Overrides::default(),
+            &mut Trace::default(),
+        ));
+        assert!(!text.starts_with("Error:"), "{text}");
+        let usage = usage.unwrap();
+        assert!(
+            usage.input_tokens > 0 && usage.output_tokens > 0,
+            "{usage:?}"
+        );
+
+        let (text, usage) = futures::executor::block_on(handle_request(
+            "Hello".to_string(),
+            Overrides {
+                max_cost_usd: Some(1e-12),
+                ..Overrides::default()
+            },
+            &mut Trace::default(),
+        ));
+        assert!(text.starts_with("Error:"), "{text}");
+        assert_eq!(usage, None);
+    }
+
+    fn cut(text: &str, stop: &[&str]) -> String {
+        let stop: Vec<String> = stop.iter().map(|s| s.to_string()).collect();
+        cut_at_stop(text.to_string(), &stop)
+    }
+
+    #[test]
+    fn cuts_before_multi_character_stop_sequences() {
+        assert_eq!(cut("one\n\nEND two", &["\n\nEND"]), "one");
+        assert_eq!(cut("no stop here", &["END"]), "no stop here");
+        assert_eq!(cut("END at start", &["END"]), "");
+        assert_eq!(cut("text", &[]), "text");
+        // Only the first occurrence counts.
+        assert_eq!(cut("a END b END", &["END"]), "a ");
+    }
+
+    #[test]
+    fn cuts_before_unicode_stop_sequences() {
+        assert_eq!(cut("Grüße aus Köln", &["ü"]), "Gr");
+        assert_eq!(cut("答えは四二です。以上", &["。"]), "答えは四二です");
+        assert_eq!(cut("ok 👍🏽 done", &["👍🏽"]), "ok ");
+        // Precomposed `é` is its own character; a decomposed one starts
+        // with `e`.
+        assert_eq!(cut("café", &["e"]), "café");
+        assert_eq!(cut(" e\u{301}", &["e\u{301}"]), " ");
+        assert_eq!(cut(" e\u{301}", &["e"]), " ");
+    }
+
+    #[test]
+    fn overlapping_stop_sequences_cut_at_the_earliest_start() {
+        assert_eq!(cut("xabcd", &["bcd", "abc"]), "x");
+        assert_eq!(cut("xabcd", &["abc", "bcd"]), "x");
+        // One sequence a prefix of another: both start at the same place.
+        assert_eq!(cut("x###y", &["###", "#"]), "x");
+        assert_eq!(cut("aaab", &["ab", "aab"]), "a");
+        assert_eq!(cut("日本語", &["本語", "日本"]), "");
+    }
+
+    #[test]
+    fn blank_instructions_are_left_out() {
+        let request = PromptRequest {
+            instructions: Some(" \n".to_string()),
+            ..request("Hi")
+        };
+        assert!(body(Endpoint::Responses, &request)
+            .get("instructions")
+            .is_none());
+        assert_eq!(
+            body(Endpoint::ChatCompletions, &request)["messages"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
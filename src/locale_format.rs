@@ -0,0 +1,352 @@
+//! Locale-consistent formatting of dates and numbers in answers.
+//!
+//! A small built-in table gives each supported locale a date pattern and
+//! decimal and thousands separators. The model is told to use exactly
+//! those, and the answer is then scanned for dates and numbers written
+//! otherwise. The scanner is a heuristic over digit runs: it reports what
+//! is clearly inconsistent with the locale and ignores what is ambiguous.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit, fetch, parse_complete_response,
+    Endpoint, MODEL,
+};
+
+/// Order of the day, month, and year fields in a locale's date pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+#[derive(Debug)]
+pub struct LocaleFormat {
+    pub locale: &'static str,
+    /// Human-readable pattern, for instructions and messages.
+    pub date_pattern: &'static str,
+    date_order: DateOrder,
+    date_separator: char,
+    pub decimal_separator: char,
+    pub thousands_separator: char,
+}
+
+const LOCALES: &[LocaleFormat] = &[
+    LocaleFormat {
+        locale: "en-US",
+        date_pattern: "MM/DD/YYYY",
+        date_order: DateOrder::MonthDayYear,
+        date_separator: '/',
+        decimal_separator: '.',
+        thousands_separator: ',',
+    },
+    LocaleFormat {
+        locale: "en-GB",
+        date_pattern: "DD/MM/YYYY",
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '/',
+        decimal_separator: '.',
+        thousands_separator: ',',
+    },
+    LocaleFormat {
+        locale: "de-DE",
+        date_pattern: "DD.MM.YYYY",
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '.',
+        decimal_separator: ',',
+        thousands_separator: '.',
+    },
+    LocaleFormat {
+        locale: "fr-FR",
+        date_pattern: "DD/MM/YYYY",
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '/',
+        decimal_separator: ',',
+        // Narrow no-break space.
+        thousands_separator: '\u{202f}',
+    },
+    LocaleFormat {
+        locale: "ja-JP",
+        date_pattern: "YYYY/MM/DD",
+        date_order: DateOrder::YearMonthDay,
+        date_separator: '/',
+        decimal_separator: '.',
+        thousands_separator: ',',
+    },
+];
+
+#[derive(Debug)]
+pub struct LocalizedResponse {
+    pub text: String,
+    /// Dates and numbers in `text` that do not follow the locale's format.
+    pub format_violations: Vec<String>,
+}
+
+/// Looks up `locale` (case-insensitively) in the built-in table.
+pub fn lookup(locale: &str) -> Result<&'static LocaleFormat> {
+    LOCALES
+        .iter()
+        .find(|format| format.locale.eq_ignore_ascii_case(locale))
+        .ok_or_else(|| {
+            let supported: Vec<&str> = LOCALES.iter().map(|format| format.locale).collect();
+            anyhow!(
+                "unsupported locale {locale:?}, expected one of {}",
+                supported.join(", ")
+            )
+        })
+}
+
+/// Sends `prompt` with formatting instructions for `locale` and reports
+/// dates and numbers in the answer that break them.
+pub async fn prompt_with_locale(prompt: String, locale: String) -> Result<LocalizedResponse> {
+    let format = lookup(&locale)?;
+
+    let body = json!({
+        "model": MODEL,
+        "instructions": instructions(format),
+        "input": prompt,
+        "stream": false,
+    });
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+    let text = parse_complete_response(&raw_response)?.text;
+
+    Ok(LocalizedResponse {
+        format_violations: scan(&text, format),
+        text,
+    })
+}
+
+fn instructions(format: &LocaleFormat) -> String {
+    format!(
+        "Format every date and number in your answer for the {} locale. \
+Write dates as {} using digits. Use {:?} as the decimal separator and {:?} (U+{:04X}) to group thousands. \
+Do not use any other date or number format, including ISO 8601 dates.",
+        format.locale,
+        format.date_pattern,
+        format.decimal_separator,
+        format.thousands_separator,
+        u32::from(format.thousands_separator)
+    )
+}
+
+/// Characters that may appear between the digits of one date or number.
+fn is_joiner(c: char) -> bool {
+    matches!(c, '.' | ',' | '/' | '-' | '\u{202f}' | '\u{a0}')
+}
+
+/// Finds dates and numbers in `text` that do not follow `format`.
+pub fn scan(text: &str, format: &LocaleFormat) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut violations = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        // A token is digits joined by single separators, starting and ending
+        // on a digit, so sentence punctuation after a number is not part of it.
+        let start = i;
+        let mut end = i;
+        while end < chars.len()
+            && (chars[end].is_ascii_digit()
+                || (is_joiner(chars[end]) && chars.get(end + 1).is_some_and(char::is_ascii_digit)))
+        {
+            end += 1;
+        }
+        let token: String = chars[start..end].iter().collect();
+        if let Some(problem) = check_token(&token, format) {
+            violations.push(format!("{token:?}: {problem}"));
+        }
+        i = end;
+    }
+    violations
+}
+
+fn check_token(token: &str, format: &LocaleFormat) -> Option<String> {
+    let groups: Vec<&str> = token.split(is_joiner).collect();
+    let separators: Vec<char> = token.chars().filter(|c| is_joiner(*c)).collect();
+    if separators.is_empty() {
+        return None;
+    }
+
+    if let Some(problem) = check_date(&groups, &separators, format) {
+        return problem;
+    }
+    check_number(&groups, &separators, format)
+}
+
+/// `None` when the token is not date-shaped; `Some(problem)` otherwise.
+fn check_date(
+    groups: &[&str],
+    separators: &[char],
+    format: &LocaleFormat,
+) -> Option<Option<String>> {
+    let short = |group: &str| (1..=2).contains(&group.len());
+    if groups.len() != 3 || separators[0] != separators[1] {
+        return None;
+    }
+    let year_first = groups[0].len() == 4 && short(groups[1]) && short(groups[2]);
+    let year_last = groups[2].len() == 4 && short(groups[0]) && short(groups[1]);
+    if !year_first && !year_last {
+        return None;
+    }
+
+    let expected = format!("expected a date as {}", format.date_pattern);
+    if separators[0] != format.date_separator
+        || year_first != (format.date_order == DateOrder::YearMonthDay)
+    {
+        return Some(Some(expected));
+    }
+    let (first, second): (u32, u32) = (groups[0].parse().ok()?, groups[1].parse().ok()?);
+    let month_out_of_range = match format.date_order {
+        DateOrder::MonthDayYear => first > 12,
+        DateOrder::DayMonthYear => second > 12,
+        DateOrder::YearMonthDay => second > 12,
+    };
+    Some(month_out_of_range.then_some(expected))
+}
+
+fn check_number(groups: &[&str], separators: &[char], format: &LocaleFormat) -> Option<String> {
+    let decimal = format.decimal_separator;
+    let thousands = format.thousands_separator;
+    // Ranges (`10-20`), fractions, and decimal-separated lists (`1.2.3`)
+    // are not single numbers.
+    let decimals = separators.iter().filter(|c| **c == decimal).count();
+    if separators.iter().any(|c| matches!(c, '-' | '/')) || decimals > 1 {
+        return None;
+    }
+
+    let is_thousands = |c: char| c == thousands || (is_space(c) && is_space(thousands));
+    // Grouping separators come before the decimal separator, if any, and
+    // each is followed by exactly three digits.
+    let grouping = separators
+        .iter()
+        .position(|c| *c == decimal)
+        .map_or(separators, |index| &separators[..index]);
+    let valid = (decimals == 0 || separators.last() == Some(&decimal))
+        && grouping.iter().all(|c| is_thousands(*c))
+        && groups[1..=grouping.len()]
+            .iter()
+            .all(|group| group.len() == 3);
+
+    (!valid).then(|| format!("expected {decimal:?} for decimals and {thousands:?} for thousands"))
+}
+
+fn is_space(c: char) -> bool {
+    matches!(c, '\u{202f}' | '\u{a0}')
+}
+
+impl From<LocalizedResponse> for wit::LocalizedResponse {
+    fn from(response: LocalizedResponse) -> Self {
+        Self {
+            text: response.text,
+            format_violations: response.format_violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violations(text: &str, locale: &str) -> Vec<String> {
+        scan(text, lookup(locale).unwrap())
+    }
+
+    #[test]
+    fn looks_up_locales_case_insensitively() {
+        assert_eq!(lookup("en-us").unwrap().locale, "en-US");
+        assert_eq!(lookup("DE-de").unwrap().locale, "de-DE");
+        assert_eq!(lookup("ja-JP").unwrap().locale, "ja-JP");
+        let error = lookup("pt-BR").unwrap_err().to_string();
+        assert!(
+            error.contains("en-US, en-GB, de-DE, fr-FR, ja-JP"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn instructs_the_locale_formats() {
+        let en = instructions(lookup("en-US").unwrap());
+        assert!(en.contains("en-US locale"), "{en}");
+        assert!(en.contains("MM/DD/YYYY"), "{en}");
+        assert!(
+            en.contains("'.' as the decimal separator and ',' (U+002C)"),
+            "{en}"
+        );
+
+        let de = instructions(lookup("de-DE").unwrap());
+        assert!(de.contains("DD.MM.YYYY"), "{de}");
+        assert!(
+            de.contains("',' as the decimal separator and '.' (U+002E)"),
+            "{de}"
+        );
+
+        let ja = instructions(lookup("ja-JP").unwrap());
+        assert!(ja.contains("YYYY/MM/DD"), "{ja}");
+    }
+
+    #[test]
+    fn en_us_accepts_its_formats_and_reports_others() {
+        assert!(
+            violations("On 07/04/2026 we sold 1,234.56 units for $12,000.", "en-US").is_empty()
+        );
+        assert_eq!(
+            violations(
+                "Shipped 2026-07-04, due 13/04/2026, cost 1.234,56.",
+                "en-US"
+            ),
+            [
+                "\"2026-07-04\": expected a date as MM/DD/YYYY",
+                "\"13/04/2026\": expected a date as MM/DD/YYYY",
+                "\"1.234,56\": expected '.' for decimals and ',' for thousands",
+            ]
+        );
+    }
+
+    #[test]
+    fn de_de_accepts_its_formats_and_reports_others() {
+        assert!(violations(
+            "Am 04.07.2026 kostete es 1.234,56 € (rund 1.235 €).",
+            "de-DE"
+        )
+        .is_empty());
+        assert_eq!(
+            violations("Am 07/04/2026 stieg der Wert um 3.5 Prozent.", "de-DE"),
+            [
+                "\"07/04/2026\": expected a date as DD.MM.YYYY",
+                "\"3.5\": expected ',' for decimals and '.' for thousands",
+            ]
+        );
+    }
+
+    #[test]
+    fn ja_jp_accepts_its_formats_and_reports_others() {
+        assert!(violations("2026/07/04 に 1,234.5 円を支払いました。", "ja-JP").is_empty());
+        assert_eq!(
+            violations("04/07/2026 と 2026/13/01 の売上", "ja-JP"),
+            [
+                "\"04/07/2026\": expected a date as YYYY/MM/DD",
+                "\"2026/13/01\": expected a date as YYYY/MM/DD",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_ambiguous_tokens() {
+        for locale in ["en-US", "de-DE", "ja-JP"] {
+            assert!(
+                violations("Pages 10-20, ratio 3/4, in 2026.", locale).is_empty(),
+                "{locale}"
+            );
+        }
+        // Several decimal separators make a list or version, not a number.
+        assert!(violations("Version 1.2.3", "en-US").is_empty());
+        assert!(violations("Version 1,2,3", "de-DE").is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Translation of i18n resource strings into several locales.
+//!
+//! Each locale is translated in its own structured output request and the
+//! requests run concurrently. As with training data, the Batch API is not
+//! used because its asynchronous completion does not fit a single
+//! invocation.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Result};
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::structured;
+
+#[derive(Deserialize)]
+struct Translation {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Translations {
+    translations: Vec<Translation>,
+}
+
+const INSTRUCTIONS: &str = "You are a professional software localizer. \
+Translate each resource string into the target locale. Keep every key unchanged, \
+preserve placeholders (such as {name}, %s, or {{count}}), markup, and surrounding whitespace exactly, \
+and match the register usual for user interfaces in that locale.";
+
+/// Translates `strings` (`key`, source text) into each of `target_languages`,
+/// returning `locale → key → translation`.
+pub async fn localize(
+    strings: Vec<(String, String)>,
+    target_languages: Vec<String>,
+) -> Result<HashMap<String, HashMap<String, String>>> {
+    ensure!(!strings.is_empty(), "no strings to translate");
+    ensure!(!target_languages.is_empty(), "no target locales given");
+    for locale in &target_languages {
+        ensure!(
+            is_bcp47(locale),
+            "{locale:?} is not a valid BCP-47 language tag"
+        );
+    }
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "translations": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "key": { "type": "string" },
+                        "value": { "type": "string" }
+                    },
+                    "required": ["key", "value"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["translations"],
+        "additionalProperties": false
+    });
+    let source = serde_json::to_string(
+        &strings
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect::<Vec<_>>(),
+    )?;
+
+    let requests = target_languages.into_iter().map(|locale| {
+        let input = format!("Target locale: {locale}\nStrings:\n{source}");
+        let schema = schema.clone();
+        let strings = &strings;
+        async move {
+            let translations: Translations =
+                structured::request("translations", INSTRUCTIONS, &input, schema).await?;
+            let mut by_key: HashMap<String, String> = translations
+                .translations
+                .into_iter()
+                .map(|translation| (translation.key, translation.value))
+                .collect();
+            let mut resolved = HashMap::with_capacity(strings.len());
+            for (key, _) in strings {
+                let Some(value) = by_key.remove(key) else {
+                    bail!("translation for {locale} is missing key {key:?}");
+                };
+                resolved.insert(key.clone(), value);
+            }
+            Ok((locale, resolved))
+        }
+    });
+
+    Ok(try_join_all(requests).await?.into_iter().collect())
+}
+
+/// Checks the shape of a BCP-47 tag: a 2–3 or 5–8 letter language subtag
+/// (or `x` for a private-use tag), an optional 4-letter script, an optional
+/// 2-letter or 3-digit region, then alphanumeric subtags of 1–8 characters.
+/// Registry membership is not checked.
+fn is_bcp47(tag: &str) -> bool {
+    let alpha = |s: &str| s.chars().all(|c| c.is_ascii_alphabetic());
+    let alnum =
+        |s: &str| !s.is_empty() && s.len() <= 8 && s.chars().all(|c| c.is_ascii_alphanumeric());
+
+    let mut subtags = tag.split('-').peekable();
+    let Some(language) = subtags.next() else {
+        return false;
+    };
+    if language.eq_ignore_ascii_case("x") {
+        return subtags.peek().is_some() && subtags.all(alnum);
+    }
+    if !alpha(language) || !matches!(language.len(), 2 | 3 | 5..=8) {
+        return false;
+    }
+    subtags.next_if(|script| script.len() == 4 && alpha(script));
+    subtags.next_if(|region| {
+        (region.len() == 2 && alpha(region))
+            || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+    });
+    subtags.all(alnum)
+}
@@ -0,0 +1,523 @@
+//! Pluggable request/response transformation for `prompt-handle`.
+//!
+//! Middleware registered with [`register_middleware`] runs in registration
+//! order: `transform_request` before the request is sent and
+//! `transform_response` on the extracted answer text. An error from any
+//! middleware aborts the call. The built-in middleware is registered from
+//! environment variables on first use (see [`install_from_env`]).
+
+use std::cell::{Cell, RefCell};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    cost,
+    policy::{PolicyReport, PolicyStage},
+};
+
+/// A prompt about to be sent to the API.
+#[derive(Debug, Clone)]
+pub struct PromptRequest {
+    pub model: String,
+    pub input: String,
+    /// System instructions, sent as the `instructions` field when non-empty.
+    pub instructions: Option<String>,
+    /// Sampling temperature in `[0, 2]`; the API default when unset.
+    pub temperature: Option<f32>,
+    /// Nucleus sampling mass in `[0, 1]`; the API default when unset.
+    pub top_p: Option<f32>,
+    /// Cap on generated tokens; unlimited when unset.
+    pub max_output_tokens: Option<u32>,
+    /// Sequences that end the answer, at most four; none when empty.
+    pub stop: Vec<String>,
+}
+
+pub trait Middleware {
+    fn transform_request(&self, request: &mut PromptRequest) -> Result<()>;
+    fn transform_response(&self, response: &mut String) -> Result<()>;
+
+    /// Name reported by the `capabilities` export; the type name by default.
+    fn name(&self) -> &'static str {
+        let path = std::any::type_name::<Self>();
+        path.rsplit("::").next().unwrap_or(path)
+    }
+}
+
+thread_local! {
+    static MIDDLEWARE: RefCell<Vec<Box<dyn Middleware>>> = RefCell::new(Vec::new());
+    static ENV_INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Appends `m` to the global middleware chain.
+pub fn register_middleware(m: Box<dyn Middleware>) {
+    MIDDLEWARE.with(|chain| chain.borrow_mut().push(m));
+}
+
+/// Registers the built-in middleware enabled through the environment, once
+/// per instance:
+///
+/// - `OPENAI_LOG_REQUESTS=true`: [`LoggingMiddleware`]
+/// - `OPENAI_REDACT_PII=true`: [`PiiRedactMiddleware`]
+/// - `OPENAI_MAX_COST_USD=<usd>`: [`CostBudgetMiddleware`]
+/// - `OPENAI_OUTPUT_TEMPLATE=<template>`: [`OutputTemplateMiddleware`],
+///   escaping per `OPENAI_OUTPUT_TEMPLATE_FORMAT` (`json`, `html`, or
+///   `none`, the default); registered last so it wraps the final answer
+///
+/// The whole chain is validated before any of it is registered, so an
+/// invalid variable fails every call until it is fixed instead of only the
+/// first one.
+pub fn install_from_env() -> Result<()> {
+    install(|name| std::env::var(name).ok())
+}
+
+fn install(var: impl Fn(&str) -> Option<String>) -> Result<()> {
+    if ENV_INSTALLED.with(Cell::get) {
+        return Ok(());
+    }
+    for m in chain_from(var)? {
+        register_middleware(m);
+    }
+    ENV_INSTALLED.with(|installed| installed.set(true));
+    Ok(())
+}
+
+/// The built-in middleware enabled by `var`, in chain order.
+fn chain_from(var: impl Fn(&str) -> Option<String>) -> Result<Vec<Box<dyn Middleware>>> {
+    let mut chain: Vec<Box<dyn Middleware>> = Vec::new();
+    let enabled = |name: &str| var(name).is_some_and(|value| value == "true");
+    if enabled("OPENAI_LOG_REQUESTS") {
+        chain.push(Box::new(LoggingMiddleware));
+    }
+    if enabled("OPENAI_REDACT_PII") {
+        chain.push(Box::new(PiiRedactMiddleware));
+    }
+    if let Some(value) = var("OPENAI_MAX_COST_USD") {
+        let Ok(max_cost_usd) = value.parse::<f64>() else {
+            bail!("OPENAI_MAX_COST_USD must be a number, got {value:?}");
+        };
+        chain.push(Box::new(CostBudgetMiddleware { max_cost_usd }));
+    }
+    if let Some(template) = var("OPENAI_OUTPUT_TEMPLATE") {
+        let format = match var("OPENAI_OUTPUT_TEMPLATE_FORMAT").as_deref() {
+            None | Some("none") => TemplateFormat::None,
+            Some("json") => TemplateFormat::Json,
+            Some("html") => TemplateFormat::Html,
+            Some(other) => {
+                bail!("OPENAI_OUTPUT_TEMPLATE_FORMAT must be json, html, or none, got {other:?}")
+            }
+        };
+        chain.push(Box::new(OutputTemplateMiddleware::parse(
+            &template, format,
+        )?));
+    }
+    Ok(chain)
+}
+
+/// Names of the registered middleware, in chain order.
+pub(crate) fn registered_names() -> Vec<&'static str> {
+    MIDDLEWARE.with(|chain| chain.borrow().iter().map(|m| m.name()).collect())
+}
+
+pub(crate) fn apply_request(request: &mut PromptRequest) -> Result<()> {
+    MIDDLEWARE.with(|chain| {
+        chain
+            .borrow()
+            .iter()
+            .try_for_each(|m| m.transform_request(request))
+    })
+}
+
+pub(crate) fn apply_response(response: &mut String) -> Result<()> {
+    MIDDLEWARE.with(|chain| {
+        chain
+            .borrow()
+            .iter()
+            .try_for_each(|m| m.transform_response(response))
+    })
+}
+
+/// Logs the model and sizes of each request and response.
+pub struct LoggingMiddleware;
+
+impl Middleware for LoggingMiddleware {
+    fn transform_request(&self, request: &mut PromptRequest) -> Result<()> {
+        eprintln!(
+            "[COMPONENT] Request to {}: {} chars",
+            request.model,
+            request.input.chars().count()
+        );
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut String) -> Result<()> {
+        eprintln!("[COMPONENT] Response: {} chars", response.chars().count());
+        Ok(())
+    }
+}
+
+/// Replaces email addresses and long digit runs (phone, card, and account
+/// numbers) with placeholders in both directions.
+pub struct PiiRedactMiddleware;
+
+/// Digit runs at least this long are treated as identifying numbers.
+const MIN_REDACTED_DIGITS: usize = 9;
+
+impl PiiRedactMiddleware {
+    fn redact(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let token_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (token, tail) = rest.split_at(token_end);
+            out.push_str(&Self::redact_token(token));
+
+            let space_end = tail
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(tail.len());
+            out.push_str(&tail[..space_end]);
+            rest = &tail[space_end..];
+        }
+
+        Self::redact_digit_runs(&out)
+    }
+
+    fn redact_token(token: &str) -> String {
+        let trimmed = token.trim_matches(|c: char| !c.is_alphanumeric());
+        let is_email = trimmed.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+        });
+
+        if is_email {
+            token.replacen(trimmed, "[REDACTED_EMAIL]", 1)
+        } else {
+            token.to_string()
+        }
+    }
+
+    /// Redacts runs of digits, allowing single spaces, dashes, and dots as
+    /// separators (`+1 555-123-4567`, `4111 1111 1111 1111`).
+    fn redact_digit_runs(text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            if !chars[i].is_ascii_digit() {
+                out.push(chars[i]);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            let mut end = i;
+            let mut digits = 0;
+            while end < chars.len() {
+                if chars[end].is_ascii_digit() {
+                    digits += 1;
+                    end += 1;
+                } else if matches!(chars[end], ' ' | '-' | '.')
+                    && chars.get(end + 1).is_some_and(char::is_ascii_digit)
+                {
+                    end += 1;
+                } else {
+                    break;
+                }
+            }
+
+            if digits >= MIN_REDACTED_DIGITS {
+                if out.ends_with('+') {
+                    out.pop();
+                }
+                out.push_str("[REDACTED_NUMBER]");
+            } else {
+                out.extend(&chars[start..end]);
+            }
+            i = end;
+        }
+
+        out
+    }
+}
+
+impl Middleware for PiiRedactMiddleware {
+    fn transform_request(&self, request: &mut PromptRequest) -> Result<()> {
+        request.input = Self::redact(&request.input);
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut String) -> Result<()> {
+        *response = Self::redact(response);
+        Ok(())
+    }
+}
+
+/// Rejects requests whose estimated cost exceeds `max_cost_usd`. This is a
+/// check of the prompt alone, before sending; the worst-case cost of every
+/// upstream call is also capped by the request ceiling (see
+/// [`request_budget`](crate::request_budget)).
+pub struct CostBudgetMiddleware {
+    pub max_cost_usd: f64,
+}
+
+impl Middleware for CostBudgetMiddleware {
+    fn transform_request(&self, request: &mut PromptRequest) -> Result<()> {
+        let estimate = cost::estimate_request_cost(&request.input, None, &request.model);
+        if estimate.estimated_cost_usd > self.max_cost_usd {
+            return Err(PolicyReport {
+                policy: "cost-budget",
+                rules: vec!["OPENAI_MAX_COST_USD".to_string()],
+                stage: PolicyStage::PreSend,
+                detail: format!(
+                    "estimated cost ${:.6} exceeds the ${:.6} budget",
+                    estimate.estimated_cost_usd, self.max_cost_usd
+                ),
+                remediation: "Shorten the prompt or raise OPENAI_MAX_COST_USD.",
+            }
+            .reject());
+        }
+        Ok(())
+    }
+
+    fn transform_response(&self, _response: &mut String) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Escaping applied to values substituted into an output template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateFormat {
+    /// Inside a JSON string literal: the template supplies the quotes.
+    Json,
+    Html,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    Answer,
+    Model,
+}
+
+/// Embeds the answer in a fixed template such as a JSON envelope or an HTML
+/// fragment, so the model never has to produce the wrapper. `{{answer}}`
+/// and `{{model}}` are replaced, escaped for the template's format.
+pub struct OutputTemplateMiddleware {
+    parts: Vec<TemplatePart>,
+    format: TemplateFormat,
+    model: RefCell<String>,
+}
+
+impl OutputTemplateMiddleware {
+    /// Parses `template`; unknown or unclosed placeholders are errors.
+    pub fn parse(template: &str, format: TemplateFormat) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find("{{") {
+            if open > 0 {
+                parts.push(TemplatePart::Literal(rest[..open].to_string()));
+            }
+            let Some(close) = rest[open..].find("}}") else {
+                bail!(
+                    "output template has an unclosed `{{{{` at byte {}",
+                    template.len() - rest.len() + open
+                );
+            };
+            parts.push(match rest[open + 2..open + close].trim() {
+                "answer" => TemplatePart::Answer,
+                "model" => TemplatePart::Model,
+                other => bail!(
+                    "output template has unknown placeholder {{{{{other}}}}}, expected {{{{answer}}}} or {{{{model}}}}"
+                ),
+            });
+            rest = &rest[open + close + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        if !parts.contains(&TemplatePart::Answer) {
+            bail!("output template must contain {{{{answer}}}}");
+        }
+
+        Ok(Self {
+            parts,
+            format,
+            model: RefCell::new(String::new()),
+        })
+    }
+
+    fn escape(&self, value: &str) -> String {
+        match self.format {
+            TemplateFormat::None => value.to_string(),
+            TemplateFormat::Json => {
+                let quoted = serde_json::to_string(value).expect("strings always serialize");
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            TemplateFormat::Html => value
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;"),
+        }
+    }
+}
+
+impl Middleware for OutputTemplateMiddleware {
+    fn transform_request(&self, request: &mut PromptRequest) -> Result<()> {
+        self.model.replace(request.model.clone());
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut String) -> Result<()> {
+        let model = self.model.borrow();
+        *response = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(text) => text.clone(),
+                TemplatePart::Answer => self.escape(response),
+                TemplatePart::Model => self.escape(&model),
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn chain_follows_the_documented_order() {
+        let chain = chain_from(vars(&[
+            ("OPENAI_OUTPUT_TEMPLATE", "{{answer}}"),
+            ("OPENAI_MAX_COST_USD", "0.5"),
+            ("OPENAI_REDACT_PII", "true"),
+            ("OPENAI_LOG_REQUESTS", "true"),
+        ]))
+        .unwrap();
+        let names: Vec<_> = chain.iter().map(|m| m.name()).collect();
+        assert_eq!(
+            names,
+            [
+                "LoggingMiddleware",
+                "PiiRedactMiddleware",
+                "CostBudgetMiddleware",
+                "OutputTemplateMiddleware"
+            ]
+        );
+        assert!(chain_from(vars(&[("OPENAI_LOG_REQUESTS", "1")]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_config_fails_every_install_and_registers_nothing() {
+        let bad = [
+            ("OPENAI_LOG_REQUESTS", "true"),
+            ("OPENAI_MAX_COST_USD", "lots"),
+        ];
+        for _ in 0..2 {
+            let error = install(vars(&bad)).unwrap_err().to_string();
+            assert!(error.contains("OPENAI_MAX_COST_USD"), "{error}");
+            assert!(registered_names().is_empty());
+        }
+
+        install(vars(&[("OPENAI_LOG_REQUESTS", "true")])).unwrap();
+        assert_eq!(registered_names(), ["LoggingMiddleware"]);
+        // Installed once: later calls keep the chain as it is.
+        install(vars(&bad)).unwrap();
+        assert_eq!(registered_names(), ["LoggingMiddleware"]);
+    }
+
+    fn render(template: &str, format: TemplateFormat, model: &str, answer: &str) -> String {
+        let middleware = OutputTemplateMiddleware::parse(template, format).unwrap();
+        let mut request = PromptRequest {
+            model: model.to_string(),
+            input: String::new(),
+            instructions: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            stop: Vec::new(),
+        };
+        middleware.transform_request(&mut request).unwrap();
+        let mut response = answer.to_string();
+        middleware.transform_response(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn template_escapes_per_format() {
+        let answer = "Say \"hi\" <b>&</b> it's\nnext\\line";
+        assert_eq!(
+            render(
+                r#"{"model":"{{model}}","answer":"{{ answer }}"}"#,
+                TemplateFormat::Json,
+                "gpt-4.1",
+                answer
+            ),
+            r#"{"model":"gpt-4.1","answer":"Say \"hi\" <b>&</b> it's\nnext\\line"}"#
+        );
+        let json: serde_json::Value = serde_json::from_str(&render(
+            r#"{"answer":"{{answer}}"}"#,
+            TemplateFormat::Json,
+            "m",
+            answer,
+        ))
+        .unwrap();
+        assert_eq!(json["answer"], answer);
+        assert_eq!(
+            render("<p>{{answer}}</p>", TemplateFormat::Html, "m", answer),
+            "<p>Say &quot;hi&quot; &lt;b&gt;&amp;&lt;/b&gt; it&#39;s\nnext\\line</p>"
+        );
+        assert_eq!(
+            render(
+                "[{{model}}] {{answer}}",
+                TemplateFormat::None,
+                "<m>",
+                answer
+            ),
+            format!("[<m>] {answer}")
+        );
+    }
+
+    #[test]
+    fn template_errors_are_config_errors_at_install() {
+        for (template, expected) in [
+            ("no placeholder", "must contain {{answer}}"),
+            ("{{answer}} {{usage}}", "unknown placeholder {{usage}}"),
+            ("{{answer", "unclosed `{{` at byte 0"),
+        ] {
+            let error = OutputTemplateMiddleware::parse(template, TemplateFormat::None)
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(error.contains(expected), "{template:?}: {error}");
+            for _ in 0..2 {
+                assert!(install(vars(&[("OPENAI_OUTPUT_TEMPLATE", template)])).is_err());
+            }
+        }
+        let error = chain_from(vars(&[
+            ("OPENAI_OUTPUT_TEMPLATE", "{{answer}}"),
+            ("OPENAI_OUTPUT_TEMPLATE_FORMAT", "xml"),
+        ]))
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("OPENAI_OUTPUT_TEMPLATE_FORMAT"), "{error}");
+        assert!(registered_names().is_empty());
+    }
+}
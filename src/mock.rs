@@ -0,0 +1,288 @@
+//! Deterministic mock provider, selected with `AI_PROVIDER=mock`.
+//!
+//! Answers Responses, Chat Completions, and Embeddings request bodies locally
+//! with responses shaped like the real API's, so the rest of the pipeline
+//! (parsing, middleware, structured output validation) runs unchanged. Configuration:
+//!
+//! - `MOCK_RESPONSE_TEMPLATE`: answer text, with `{input}` replaced by the
+//!   request input and `{model}` by the requested model
+//!   (default `Mock response to: {input}`)
+//! - `MOCK_LATENCY_MS`: artificial delay before answering (default `0`)
+//! - `MOCK_FAILURE_RATE`: probability in `[0, 1]` of failing with an HTTP 500
+//!   (default `0`)
+//!
+//! Structured output requests are answered with a value built from their
+//! JSON schema (see [`sample`]). Streaming requests get the answer as one
+//! text delta event per word.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    bindings::wasi::random::random, error::OpenAiError, executor, request_hash, sha256,
+    tokens::count_tokens, Endpoint,
+};
+
+const DEFAULT_TEMPLATE: &str = "Mock response to: {input}";
+
+/// Vector length of mock embeddings when the request does not set
+/// `dimensions` (matches `text-embedding-3-small`).
+const MOCK_EMBEDDING_DIMENSIONS: u64 = 1536;
+
+pub(crate) async fn respond(endpoint: Endpoint, json_body: &[u8]) -> Result<Vec<u8>> {
+    let request: Value = serde_json::from_slice(json_body).context("invalid request body")?;
+
+    let latency_ms: u64 = env_or("MOCK_LATENCY_MS", 0)?;
+    if latency_ms > 0 {
+        executor::sleep(Duration::from_millis(latency_ms)).await;
+    }
+
+    let failure_rate: f64 = env_or("MOCK_FAILURE_RATE", 0.0)?;
+    if !(0.0..=1.0).contains(&failure_rate) {
+        bail!("MOCK_FAILURE_RATE must be between 0 and 1, got {failure_rate}");
+    }
+    if failure_rate > 0.0 && (random::get_random_u64() as f64 / u64::MAX as f64) < failure_rate {
+        eprintln!("[COMPONENT] Mock provider failing request");
+        return Err(injected_failure().into());
+    }
+
+    let response = match endpoint {
+        Endpoint::Responses => respond_responses(&request),
+        Endpoint::ChatCompletions => respond_chat_completions(&request),
+        Endpoint::Embeddings => respond_embeddings(&request),
+    };
+
+    if request["stream"] == true {
+        return Ok(stream_events(endpoint, &response).into_bytes());
+    }
+    Ok(serde_json::to_vec(&response)?)
+}
+
+/// The failure `MOCK_FAILURE_RATE` injects: an HTTP 500 like the real API's.
+fn injected_failure() -> OpenAiError {
+    OpenAiError::HttpError {
+        status: 500,
+        body: "INJECTED by the mock provider (MOCK_FAILURE_RATE)".to_string(),
+    }
+}
+
+/// Renders `response` as the server-sent events of a streaming request: one
+/// text delta per word, then `response.completed` on the Responses API or
+/// `[DONE]` on Chat Completions.
+fn stream_events(endpoint: Endpoint, response: &Value) -> String {
+    let text = response
+        .pointer("/output/0/content/0/text")
+        .or_else(|| response.pointer("/choices/0/message/content"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    let mut events = String::new();
+    let mut push = |event: Value| {
+        match event["type"].as_str() {
+            Some(kind) => events.push_str(&format!("event: {kind}\ndata: {event}\n\n")),
+            None => events.push_str(&format!("data: {event}\n\n")),
+        };
+    };
+    for word in text.split_inclusive(' ') {
+        push(match endpoint {
+            Endpoint::ChatCompletions => json!({ "choices": [{ "delta": { "content": word } }] }),
+            _ => json!({ "type": "response.output_text.delta", "delta": word }),
+        });
+    }
+    match endpoint {
+        Endpoint::ChatCompletions => events.push_str("data: [DONE]\n\n"),
+        _ => push(json!({ "type": "response.completed", "response": response })),
+    }
+    events
+}
+
+/// `MOCK_RESPONSE_TEMPLATE` filled in for `input` and `model`.
+fn render(input: &str, model: &str) -> String {
+    std::env::var("MOCK_RESPONSE_TEMPLATE")
+        .unwrap_or_else(|_| DEFAULT_TEMPLATE.to_string())
+        .replace("{input}", input)
+        .replace("{model}", model)
+}
+
+fn respond_responses(request: &Value) -> Value {
+    let model = request["model"].as_str().unwrap_or_default();
+    let input = match &request["input"] {
+        Value::String(input) => input.clone(),
+        other => other.to_string(),
+    };
+    let rendered = render(&input, model);
+
+    let text = match request.pointer("/text/format/schema") {
+        Some(schema) => sample(schema, &rendered).to_string(),
+        None => rendered,
+    };
+
+    let input_tokens = count_tokens(&input);
+    let output_tokens = count_tokens(&text);
+    json!({
+        "id": format!("resp_mock_{}", &request_hash::request_hash(request)[..16]),
+        "object": "response",
+        "status": "completed",
+        "model": model,
+        "output": [{
+            "type": "message",
+            "role": "assistant",
+            "status": "completed",
+            "content": [{ "type": "output_text", "text": text, "annotations": [] }]
+        }],
+        "usage": {
+            "input_tokens": input_tokens,
+            "output_tokens": output_tokens,
+            "total_tokens": input_tokens + output_tokens
+        }
+    })
+}
+
+/// Answers the last user message of a Chat Completions request.
+fn respond_chat_completions(request: &Value) -> Value {
+    let model = request["model"].as_str().unwrap_or_default();
+    let input = request["messages"]
+        .as_array()
+        .and_then(|messages| {
+            messages
+                .iter()
+                .rev()
+                .find(|message| message["role"] == "user")
+        })
+        .map(|message| match &message["content"] {
+            Value::String(content) => content.clone(),
+            other => other.to_string(),
+        })
+        .unwrap_or_default();
+    let text = render(&input, model);
+
+    let prompt_tokens = count_tokens(&input);
+    let completion_tokens = count_tokens(&text);
+    json!({
+        "id": format!("chatcmpl_mock_{}", &request_hash::request_hash(request)[..16]),
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": text },
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        }
+    })
+}
+
+/// Embeddings derived from a hash of each input, so equal inputs always get
+/// equal vectors. Honors the `dimensions` request field.
+fn respond_embeddings(request: &Value) -> Value {
+    let inputs: Vec<String> = match &request["input"] {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map_or_else(|| item.to_string(), str::to_string)
+            })
+            .collect(),
+        Value::String(input) => vec![input.clone()],
+        other => vec![other.to_string()],
+    };
+    let dimensions = request["dimensions"]
+        .as_u64()
+        .unwrap_or(MOCK_EMBEDDING_DIMENSIONS);
+
+    let data: Vec<Value> = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let embedding: Vec<f32> = (0..dimensions.div_ceil(32))
+                .flat_map(|block| sha256::digest(format!("{block}:{input}").as_bytes()))
+                .take(usize::try_from(dimensions).unwrap_or(usize::MAX))
+                .map(|byte| f32::from(byte) / 127.5 - 1.0)
+                .collect();
+            json!({ "object": "embedding", "index": index, "embedding": embedding })
+        })
+        .collect();
+
+    let tokens: u32 = inputs.iter().map(|input| count_tokens(input)).sum();
+    json!({
+        "object": "list",
+        "data": data,
+        "model": request["model"],
+        "usage": { "prompt_tokens": tokens, "total_tokens": tokens }
+    })
+}
+
+/// Builds a value matching `schema`: strings are `text`, numbers take their
+/// `minimum` (or zero), enums their first value, and arrays one element.
+fn sample(schema: &Value, text: &str) -> Value {
+    if let Some(first) = schema["enum"].get(0) {
+        return first.clone();
+    }
+
+    match schema["type"].as_str() {
+        Some("object") => Value::Object(
+            schema["properties"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| (name.clone(), sample(property, text)))
+                .collect::<Map<_, _>>(),
+        ),
+        Some("array") => Value::Array(vec![sample(&schema["items"], text)]),
+        Some("integer") | Some("number") => {
+            schema.get("minimum").cloned().unwrap_or_else(|| json!(0))
+        }
+        Some("boolean") => Value::Bool(false),
+        Some("null") => Value::Null,
+        _ => Value::String(text.to_string()),
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("{name} has invalid value {value:?}")),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explain::ErrorCode;
+
+    #[test]
+    fn streams_in_the_shape_of_the_endpoint() {
+        let request = br#"{"model": "gpt-4.1", "input": "a b", "stream": true}"#;
+        let events = String::from_utf8(
+            futures::executor::block_on(respond(Endpoint::Responses, request)).unwrap(),
+        )
+        .unwrap();
+        assert!(events.contains("response.output_text.delta"), "{events}");
+        assert!(events.ends_with("\n\n") && events.contains("response.completed"));
+
+        let request = br#"{"model": "gpt-4.1", "messages": [{"role": "user", "content": "a b"}], "stream": true}"#;
+        let events = String::from_utf8(
+            futures::executor::block_on(respond(Endpoint::ChatCompletions, request)).unwrap(),
+        )
+        .unwrap();
+        assert!(events.contains(r#""delta":{"content":"#), "{events}");
+        assert!(events.ends_with("data: [DONE]\n\n"), "{events}");
+    }
+
+    #[test]
+    fn injected_failures_are_http_errors() {
+        let error = anyhow::Error::from(injected_failure());
+        assert_eq!(ErrorCode::of(&error), ErrorCode::HttpError);
+        assert!(matches!(
+            error.downcast_ref::<OpenAiError>(),
+            Some(OpenAiError::HttpError { status: 500, body }) if body.starts_with("INJECTED")
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+//! Onboarding plans for new employees, broken down week by week.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct OnboardingWeek {
+    pub week_number: u8,
+    pub goals: Vec<String>,
+    pub activities: Vec<String>,
+    pub check_in_questions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OnboardingPlan {
+    pub weeks: Vec<OnboardingWeek>,
+}
+
+const INSTRUCTIONS: &str = "You are an engineering manager preparing an onboarding plan. \
+Plan the requested number of weeks for a new hire in the given role and team, numbering weeks from 1. \
+Each week needs concrete goals, the activities that achieve them, and questions for the weekly check-in.";
+
+/// Asks the model for an onboarding plan covering `duration_days`, which
+/// must be a whole number of weeks.
+pub async fn generate_onboarding(
+    role: String,
+    team: String,
+    duration_days: u8,
+) -> Result<OnboardingPlan> {
+    ensure!(
+        duration_days > 0 && duration_days.is_multiple_of(7),
+        "duration_days must be a positive multiple of 7, got {duration_days}"
+    );
+    let week_count = duration_days / 7;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "weeks": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "week_number": { "type": "integer" },
+                        "goals": { "type": "array", "items": { "type": "string" } },
+                        "activities": { "type": "array", "items": { "type": "string" } },
+                        "check_in_questions": { "type": "array", "items": { "type": "string" } }
+                    },
+                    "required": ["week_number", "goals", "activities", "check_in_questions"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["weeks"],
+        "additionalProperties": false
+    });
+
+    let input = format!("Role: {role}\nTeam: {team}\nNumber of weeks: {week_count}");
+    let plan: OnboardingPlan =
+        structured::request("onboarding_plan", INSTRUCTIONS, &input, schema).await?;
+
+    ensure!(
+        plan.weeks.len() == usize::from(week_count),
+        "expected {week_count} weeks in the plan, got {}",
+        plan.weeks.len()
+    );
+    for (expected, week) in (1..=week_count).zip(&plan.weeks) {
+        ensure!(
+            week.week_number == expected,
+            "week {expected} is numbered {}",
+            week.week_number
+        );
+    }
+
+    Ok(plan)
+}
+
+impl From<OnboardingWeek> for wit::OnboardingWeek {
+    fn from(week: OnboardingWeek) -> Self {
+        Self {
+            week_number: week.week_number,
+            goals: week.goals,
+            activities: week.activities,
+            check_in_questions: week.check_in_questions,
+        }
+    }
+}
+
+impl From<OnboardingPlan> for wit::OnboardingPlan {
+    fn from(plan: OnboardingPlan) -> Self {
+        Self {
+            weeks: plan.weeks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Validation of API responses against the schemas in an OpenAPI document.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit,
+    json_schema::{self, ValidationIssue},
+};
+
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Validates `response_body` against the response schema that `spec_json`
+/// (OpenAPI 3.x or Swagger 2.0, as JSON) declares for `path` and
+/// `status_code`.
+///
+/// `path` may be a concrete request path (`/users/42`); it is matched against
+/// templated paths (`/users/{id}`) when there is no exact entry. The status is
+/// looked up exactly, then as a range (`2XX`), then as `default`. When several
+/// operations on the path declare that status, the first in method order
+/// (`get`, `put`, `post`, ...) is used.
+pub fn validate_response_against_spec(
+    spec_json: String,
+    path: String,
+    status_code: u16,
+    response_body: String,
+) -> Result<Vec<ValidationIssue>> {
+    let spec: Value = serde_json::from_str(&spec_json).context("spec is not valid JSON")?;
+    let body: Value =
+        serde_json::from_str(&response_body).context("response body is not valid JSON")?;
+
+    let paths = spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("spec has no paths"))?;
+    let path_item = paths
+        .get(&path)
+        .or_else(|| {
+            paths
+                .iter()
+                .find(|(template, _)| path_matches(template, &path))
+                .map(|(_, item)| item)
+        })
+        .ok_or_else(|| anyhow!("path {path} is not defined in the spec"))?;
+
+    let status = status_code.to_string();
+    let range = format!("{}XX", status_code / 100);
+    let response = METHODS
+        .iter()
+        .filter_map(|method| path_item.get(method)?.get("responses"))
+        .find_map(|responses| {
+            responses
+                .get(&status)
+                .or_else(|| responses.get(&range))
+                .or_else(|| responses.get(range.to_lowercase()))
+                .or_else(|| responses.get("default"))
+        })
+        .ok_or_else(|| anyhow!("no response is defined for {path} with status {status_code}"))?;
+    let response = follow_ref(&spec, response)?;
+
+    // OpenAPI 3 nests schemas under media types; Swagger 2 puts it directly
+    // on the response.
+    let schema = match response.get("content").and_then(Value::as_object) {
+        Some(content) => content
+            .iter()
+            .find(|(media_type, _)| media_type.contains("json"))
+            .and_then(|(_, media)| media.get("schema")),
+        None => response.get("schema"),
+    };
+    let Some(schema) = schema else {
+        bail!("the {status_code} response for {path} has no JSON schema");
+    };
+
+    Ok(json_schema::validate(schema, &body, &spec))
+}
+
+fn follow_ref<'a>(spec: &'a Value, value: &'a Value) -> Result<&'a Value> {
+    match value.get("$ref").and_then(Value::as_str) {
+        Some(reference) => json_schema::resolve(spec, reference)
+            .ok_or_else(|| anyhow!("unresolvable $ref {reference}")),
+        None => Ok(value),
+    }
+}
+
+/// Matches `/users/{id}` against `/users/42`.
+fn path_matches(template: &str, path: &str) -> bool {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+
+    template.len() == path.len()
+        && template.iter().zip(&path).all(|(expected, actual)| {
+            (expected.starts_with('{') && expected.ends_with('}') && !actual.is_empty())
+                || expected == actual
+        })
+}
+
+impl From<ValidationIssue> for wit::ValidationIssue {
+    fn from(issue: ValidationIssue) -> Self {
+        Self {
+            field: issue.field,
+            message: issue.message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn validate(spec: &Value, path: &str, status: u16, body: Value) -> Result<Vec<String>> {
+        validate_response_against_spec(spec.to_string(), path.to_string(), status, body.to_string())
+            .map(|issues| issues.into_iter().map(|issue| issue.field).collect())
+    }
+
+    #[test]
+    fn finds_the_schema_for_templated_paths_and_status_ranges() {
+        let spec = json!({
+            "openapi": "3.0.0",
+            "paths": {"/users/{id}": {"get": {"responses": {
+                "200": {"content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}},
+                "4XX": {"$ref": "#/components/responses/Error"}
+            }}}},
+            "components": {
+                "schemas": {"User": {"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}, "nick": {"type": "string", "nullable": true}}}},
+                "responses": {"Error": {"content": {"application/json": {"schema": {"type": "object", "required": ["error"]}}}}}
+            }
+        });
+        assert!(
+            validate(&spec, "/users/42", 200, json!({"id": 42, "nick": null}))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            validate(&spec, "/users/42", 200, json!({"id": "42"})).unwrap(),
+            ["$.id"]
+        );
+        assert_eq!(
+            validate(&spec, "/users/42", 404, json!({})).unwrap(),
+            ["$.error"]
+        );
+
+        let error = validate(&spec, "/users", 200, json!({}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("not defined in the spec"), "{error}");
+        let error = validate(&spec, "/users/42", 500, json!({}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("no response is defined"), "{error}");
+    }
+
+    #[test]
+    fn reads_swagger_2_response_schemas() {
+        let spec = json!({
+            "swagger": "2.0",
+            "paths": {"/items": {"get": {"responses": {"default": {"schema": {"type": "array", "items": {"type": "string"}}}}}}}
+        });
+        assert_eq!(
+            validate(&spec, "/items/", 201, json!(["a", 1])).unwrap(),
+            ["$[1]"]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Structured reports for requests rejected by a policy.
+//!
+//! Every policy that blocks a request returns a [`PolicyReport`] as its
+//! error, so callers can recover which policy fired (by downcasting the
+//! `anyhow::Error`) and the message format and logging stay uniform.
+
+use std::fmt;
+
+use crate::bindings::exports::wasmcloud::ai::response_handler as wit;
+
+/// Whether a policy fired before the request was sent or on the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyStage {
+    PreSend,
+    PostReceive,
+}
+
+impl fmt::Display for PolicyStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PreSend => "pre-send",
+            Self::PostReceive => "post-receive",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyReport {
+    /// Identifier of the policy that fired, e.g. `cost-budget`.
+    pub policy: &'static str,
+    /// Identifiers of the matched rules or categories.
+    pub rules: Vec<String>,
+    pub stage: PolicyStage,
+    /// Human-readable details for this request.
+    pub detail: String,
+    /// What the caller can change to get the request through.
+    pub remediation: &'static str,
+}
+
+impl PolicyReport {
+    /// Logs the report and converts it into an error for returning.
+    pub fn reject(self) -> anyhow::Error {
+        eprintln!(
+            "[COMPONENT] Policy {} blocked request ({}): rules=[{}] {}",
+            self.policy,
+            self.stage,
+            self.rules.join(", "),
+            self.detail
+        );
+        self.into()
+    }
+}
+
+impl fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "blocked by {} policy ({}, rules: {}): {}. {}",
+            self.policy,
+            self.stage,
+            self.rules.join(", "),
+            self.detail,
+            self.remediation
+        )
+    }
+}
+
+impl std::error::Error for PolicyReport {}
+
+impl From<PolicyReport> for wit::PolicyReport {
+    fn from(report: PolicyReport) -> Self {
+        Self {
+            policy: report.policy.to_string(),
+            rules: report.rules,
+            stage: report.stage.to_string(),
+            detail: report.detail,
+            remediation: report.remediation.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        budget, content_filter,
+        explain::{explain_error, ErrorCode},
+        guard,
+        middleware::{CostBudgetMiddleware, Middleware, PromptRequest},
+        request_budget::Ledger,
+    };
+
+    /// The report carried by `error`, checking that its policy has an
+    /// explanation.
+    fn report(error: anyhow::Error) -> PolicyReport {
+        let code = ErrorCode::of(&error);
+        let report = error
+            .downcast::<PolicyReport>()
+            .expect("error is a policy report");
+        assert_ne!(
+            code,
+            ErrorCode::Other,
+            "{} has no error code",
+            report.policy
+        );
+        assert!(explain_error(report.policy).is_ok());
+        report
+    }
+
+    #[test]
+    fn cost_budget_reports_the_estimate_and_budget() {
+        let mut request = PromptRequest {
+            model: "gpt-4.1".to_string(),
+            input: "Summarize the history of the printing press.".to_string(),
+            instructions: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            stop: Vec::new(),
+        };
+        let middleware = CostBudgetMiddleware { max_cost_usd: 0.0 };
+        let report = report(middleware.transform_request(&mut request).unwrap_err());
+
+        assert_eq!(report.policy, "cost-budget");
+        assert_eq!(report.rules, ["OPENAI_MAX_COST_USD"]);
+        assert_eq!(report.stage, PolicyStage::PreSend);
+        assert!(
+            report.detail.starts_with("estimated cost $"),
+            "{}",
+            report.detail
+        );
+        assert!(
+            report.detail.ends_with("exceeds the $0.000000 budget"),
+            "{}",
+            report.detail
+        );
+        assert_eq!(
+            report.remediation,
+            "Shorten the prompt or raise OPENAI_MAX_COST_USD."
+        );
+    }
+
+    #[test]
+    fn request_budget_reports_the_call_over_the_ceiling() {
+        let mut ledger = Ledger {
+            ceiling_usd: Some(0.01),
+            ..Ledger::default()
+        };
+        ledger.admit(0.004).unwrap();
+        let report = ledger.admit(0.008).unwrap_err();
+
+        assert_eq!(report.policy, "request-budget");
+        assert_eq!(report.rules, ["request-budget-exceeded"]);
+        assert_eq!(report.stage, PolicyStage::PreSend);
+        assert_eq!(
+            report.detail,
+            "upstream call 2 could cost up to $0.008000 on top of $0.004000 already committed, over the ceiling of $0.010000"
+        );
+        assert!(report
+            .remediation
+            .contains("OPENAI_REQUEST_COST_CEILING_USD"));
+    }
+
+    #[test]
+    fn request_budget_reports_a_budget_below_the_minimum_useful_cost() {
+        let error =
+            futures::executor::block_on(budget::prompt_with_budget("Hello".to_string(), 0.0))
+                .unwrap_err();
+        let report = report(error);
+
+        assert_eq!(report.policy, "request-budget");
+        assert_eq!(report.rules, ["minimum-useful-cost"]);
+        assert_eq!(report.stage, PolicyStage::PreSend);
+        assert!(
+            report
+                .detail
+                .starts_with("budget of $0.000000 is below the minimum useful request cost of $"),
+            "{}",
+            report.detail
+        );
+        assert_eq!(
+            report.remediation,
+            "Raise the budget or shorten the prompt."
+        );
+    }
+
+    #[test]
+    fn content_filter_reports_the_filtered_categories() {
+        let response = json!({
+            "choices": [{
+                "message": { "content": "..." },
+                "content_filter_results": {
+                    "hate": { "filtered": false, "severity": "safe" },
+                    "violence": { "filtered": true, "severity": "medium" }
+                }
+            }]
+        });
+        let report = content_filter::suppressed(content_filter::filtered_categories(&response));
+
+        assert_eq!(report.policy, "content-filter");
+        assert_eq!(report.rules, ["violence"]);
+        assert_eq!(report.stage, PolicyStage::PostReceive);
+        assert_eq!(
+            report.detail,
+            "the provider's content filter flagged the answer (violence)"
+        );
+        assert!(report
+            .remediation
+            .contains("OPENAI_CONTENT_FILTER_TENANT_POLICIES"));
+    }
+
+    #[test]
+    fn output_guard_reports_the_blocked_categories() {
+        let report = guard::blocked(vec!["violence".to_string(), "hate".to_string()]);
+
+        assert_eq!(report.policy, "output-guard");
+        assert_eq!(report.rules, ["violence", "hate"]);
+        assert_eq!(report.stage, PolicyStage::PostReceive);
+        assert_eq!(
+            report.detail,
+            "the guard model found disallowed content (violence, hate)"
+        );
+        assert_eq!(
+            report.to_string(),
+            "blocked by output-guard policy (post-receive, rules: violence, hate): \
+             the guard model found disallowed content (violence, hate). \
+             Rephrase the prompt to avoid the blocked categories."
+        );
+    }
+
+    #[test]
+    fn output_guard_reports_an_unusable_verdict() {
+        let report = guard::unavailable(&anyhow::anyhow!("HTTP 500"));
+
+        assert_eq!(report.policy, "output-guard");
+        assert_eq!(report.rules, ["guard-unavailable"]);
+        assert_eq!(report.stage, PolicyStage::PostReceive);
+        assert_eq!(
+            report.detail,
+            "the guard model gave no usable verdict: HTTP 500"
+        );
+        assert!(report.remediation.contains("OPENAI_GUARD_FAIL_OPEN=true"));
+    }
+}
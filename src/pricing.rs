@@ -0,0 +1,68 @@
+//! Per-model token pricing.
+
+/// Pricing for one model family, in USD per million tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelPricing {
+    pub model: &'static str,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    /// Expected output tokens per input token, used when estimating a request
+    /// before it is sent.
+    pub output_ratio: f64,
+}
+
+impl ModelPricing {
+    /// Cost in USD of the given token counts.
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (f64::from(input_tokens) * self.input_per_million
+            + f64::from(output_tokens) * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Model pricing looked up by model name.
+pub struct PricingTable {
+    entries: &'static [ModelPricing],
+}
+
+const fn price(model: &'static str, input: f64, output: f64, output_ratio: f64) -> ModelPricing {
+    ModelPricing {
+        model,
+        input_per_million: input,
+        output_per_million: output,
+        output_ratio,
+    }
+}
+
+impl PricingTable {
+    /// Published OpenAI list prices. Reasoning models get a higher output
+    /// ratio because reasoning tokens are billed as output.
+    pub const OPENAI: PricingTable = PricingTable {
+        entries: &[
+            price("gpt-4.1", 2.00, 8.00, 0.5),
+            price("gpt-4.1-mini", 0.40, 1.60, 0.5),
+            price("gpt-4.1-nano", 0.10, 0.40, 0.5),
+            price("gpt-4o", 2.50, 10.00, 0.5),
+            price("gpt-4o-mini", 0.15, 0.60, 0.5),
+            price("gpt-5", 1.25, 10.00, 1.0),
+            price("gpt-5-mini", 0.25, 2.00, 1.0),
+            price("gpt-5-nano", 0.05, 0.40, 1.0),
+            price("o1", 15.00, 60.00, 2.0),
+            price("o3", 2.00, 8.00, 2.0),
+            price("o3-mini", 1.10, 4.40, 2.0),
+            price("o4-mini", 1.10, 4.40, 2.0),
+            price("text-embedding-3-small", 0.02, 0.0, 0.0),
+            price("text-embedding-3-large", 0.13, 0.0, 0.0),
+            price("text-embedding-ada-002", 0.10, 0.0, 0.0),
+        ],
+    };
+
+    /// Finds the pricing for `model`, matching dated snapshots such as
+    /// `gpt-4.1-2025-04-14` by their longest known prefix.
+    pub fn lookup(&self, model: &str) -> Option<&ModelPricing> {
+        self.entries
+            .iter()
+            .filter(|entry| model.starts_with(entry.model))
+            .max_by_key(|entry| entry.model.len())
+    }
+}
@@ -0,0 +1,205 @@
+//! Signed usage receipts for billing across components.
+//!
+//! A receipt records what one `prompt-handle` request consumed: the hash
+//! and model of the request as sent and the usage its response reported.
+//! It is signed with HMAC-SHA256 under the shared secret in
+//! `OPENAI_RECEIPT_SECRET`, over the canonical JSON form used for request
+//! hashing (see
+//! [`request_hash::canonical_json`]), so any component holding the secret
+//! can verify it regardless of how the receipt JSON was re-encoded in
+//! transit.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    bindings::{exports::wasmcloud::ai::response_handler as wit, wasi::clocks::wall_clock},
+    pricing::PricingTable,
+    request_hash, sha256,
+    trace::Trace,
+    try_handle_prompt, Answer,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Receipt {
+    pub request_hash: String,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+    /// Milliseconds since the Unix epoch. Not named `timestamp`, which
+    /// canonicalization drops.
+    pub timestamp_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct ReceiptedResponse {
+    pub text: String,
+    /// The receipt as JSON.
+    pub receipt: String,
+    /// Hex HMAC-SHA256 of the receipt's canonical form.
+    pub signature: String,
+}
+
+/// Runs `prompt` through the `prompt-handle` pipeline and returns the
+/// answer with a signed receipt for the request it sent.
+pub async fn prompt_with_receipt(prompt: String) -> Result<ReceiptedResponse> {
+    let secret = secret()?;
+    let answer = try_handle_prompt(prompt, &mut Trace::default(), false).await?;
+    let now = wall_clock::now();
+    let receipt = serde_json::to_value(receipt_for(
+        &answer,
+        now.seconds * 1000 + u64::from(now.nanoseconds / 1_000_000),
+    ))?;
+
+    Ok(ReceiptedResponse {
+        text: answer.text,
+        signature: sign(&receipt, &secret),
+        receipt: receipt.to_string(),
+    })
+}
+
+/// The receipt for `answer`, priced from its model and reported usage. An
+/// answer without usage (a soft-fail fallback) consumed nothing.
+fn receipt_for(answer: &Answer, timestamp_ms: u64) -> Receipt {
+    let usage = answer.usage.unwrap_or_default();
+    Receipt {
+        request_hash: answer.request_hash.clone(),
+        model: answer.model.clone(),
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cost_usd: PricingTable::OPENAI
+            .lookup(&answer.model)
+            .map_or(0.0, |pricing| {
+                pricing.cost(usage.input_tokens, usage.output_tokens)
+            }),
+        timestamp_ms,
+    }
+}
+
+/// Checks that `signature` is the signature of `receipt` (JSON) under the
+/// configured secret. A receipt that is not a valid receipt object fails
+/// verification rather than erroring.
+pub fn verify_receipt(receipt: &str, signature: &str) -> Result<bool> {
+    Ok(verify(receipt, signature, &secret()?))
+}
+
+fn verify(receipt: &str, signature: &str, secret: &[u8]) -> bool {
+    let Ok(receipt) = serde_json::from_str::<Value>(receipt) else {
+        return false;
+    };
+    if serde_json::from_value::<Receipt>(receipt.clone()).is_err() {
+        return false;
+    }
+
+    let expected = sign(&receipt, secret);
+    // Compare without short-circuiting so timing does not reveal a prefix.
+    expected.len() == signature.len()
+        && expected
+            .bytes()
+            .zip(signature.to_ascii_lowercase().bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn sign(receipt: &Value, secret: &[u8]) -> String {
+    sha256::hex(&sha256::hmac(
+        secret,
+        request_hash::canonical_json(receipt).as_bytes(),
+    ))
+}
+
+fn secret() -> Result<Vec<u8>> {
+    match std::env::var("OPENAI_RECEIPT_SECRET") {
+        Ok(secret) if !secret.is_empty() => Ok(secret.into_bytes()),
+        _ => Err(anyhow!(
+            "OPENAI_RECEIPT_SECRET environment variable not set"
+        )),
+    }
+}
+
+impl From<ReceiptedResponse> for wit::ReceiptedResponse {
+    fn from(response: ReceiptedResponse) -> Self {
+        Self {
+            text: response.text,
+            receipt: response.receipt,
+            signature: response.signature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Usage;
+
+    const SECRET: &[u8] = b"receipt-test-secret";
+    const REQUEST_HASH: &str = "093027befd6db319cbe09c12b291c8bea481b1a2d9392471564514bd55c54c26";
+    const TIMESTAMP_MS: u64 = 1_760_400_000_000;
+
+    fn answer(model: &str, usage: Option<Usage>) -> Answer {
+        Answer {
+            text: "Hi".to_string(),
+            model: model.to_string(),
+            request_hash: REQUEST_HASH.to_string(),
+            usage,
+            flags: Vec::new(),
+            degraded: false,
+        }
+    }
+
+    fn receipt_json(answer: &Answer) -> Value {
+        serde_json::to_value(receipt_for(answer, TIMESTAMP_MS)).unwrap()
+    }
+
+    #[test]
+    fn receipts_the_model_and_usage_of_the_request() {
+        let usage = Usage {
+            input_tokens: 1000,
+            output_tokens: 500,
+            total_tokens: 1500,
+        };
+        let receipt = receipt_json(&answer("gpt-4.1", Some(usage)));
+        assert_eq!(
+            request_hash::canonical_json(&receipt),
+            r#"{"cost_usd":0.006,"input_tokens":1000,"model":"gpt-4.1","output_tokens":500,"request_hash":"093027befd6db319cbe09c12b291c8bea481b1a2d9392471564514bd55c54c26","timestamp_ms":1760400000000}"#
+        );
+        assert_eq!(
+            sign(&receipt, SECRET),
+            "373980f2f403e6b9c3a174b3c13553903b81aa190443f04d2b449f971060a2ac"
+        );
+    }
+
+    #[test]
+    fn receipts_nothing_for_an_answer_without_usage() {
+        let receipt = receipt_json(&answer("unpriced-model", None));
+        assert_eq!(receipt["input_tokens"], 0);
+        assert_eq!(receipt["output_tokens"], 0);
+        assert_eq!(receipt["cost_usd"], 0.0);
+        assert_eq!(receipt["model"], "unpriced-model");
+    }
+
+    #[test]
+    fn verifies_only_untampered_receipts_under_the_same_secret() {
+        let receipt = receipt_json(&answer("gpt-4.1", None));
+        let signature = sign(&receipt, SECRET);
+        let encoded = receipt.to_string();
+
+        assert!(verify(&encoded, &signature, SECRET));
+        assert!(verify(&encoded, &signature.to_ascii_uppercase(), SECRET));
+        // Re-encoding with other key order and whitespace keeps the signature.
+        let reencoded = serde_json::to_string_pretty(&receipt).unwrap();
+        assert!(verify(&reencoded, &signature, SECRET));
+
+        assert!(!verify(&encoded, &signature, b"other-secret"));
+        assert!(!verify(
+            &encoded.replace("gpt-4.1", "gpt-4o"),
+            &signature,
+            SECRET
+        ));
+        assert!(!verify(&encoded, &signature[..63], SECRET));
+        assert!(!verify("not json", &signature, SECRET));
+        assert!(!verify(r#"{"model":"gpt-4.1"}"#, &signature, SECRET));
+    }
+}
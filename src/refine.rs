@@ -0,0 +1,45 @@
+//! Stateless multi-turn refinement of an answer from a list of feedback.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+use crate::{fetch, parse_complete_response, Endpoint, MODEL};
+
+const INSTRUCTIONS: &str = "You revise text according to reviewer feedback. \
+Apply the feedback to the given text and keep everything the feedback does not ask to change. \
+Reply with only the revised text.";
+
+/// Answers `initial_prompt`, then revises the answer once per entry of
+/// `refinements` in order. Returns the first answer followed by every
+/// revision.
+pub async fn interactive_refine(
+    initial_prompt: String,
+    refinements: Vec<String>,
+) -> Result<Vec<String>> {
+    let mut versions = Vec::with_capacity(refinements.len() + 1);
+    versions.push(complete(None, initial_prompt).await?);
+
+    for refinement in refinements {
+        let previous_output = versions.last().expect("first version was pushed");
+        let prompt = format!(
+            "Here is a revised version based on the feedback '{refinement}': {previous_output}"
+        );
+        versions.push(complete(Some(INSTRUCTIONS), prompt).await?);
+    }
+
+    Ok(versions)
+}
+
+async fn complete(instructions: Option<&str>, input: String) -> Result<String> {
+    let body = json!({
+        "model": MODEL,
+        "instructions": instructions,
+        "input": input,
+        "stream": false,
+    });
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+
+    parse_complete_response(&raw_response).map(|parsed| parsed.text)
+}
@@ -0,0 +1,203 @@
+//! Cost ceiling for one logical request across all of its upstream calls.
+//!
+//! A single export call can make several upstream calls: a guard-model
+//! check after the answer, an evaluation and a revision, or one call per
+//! locale or model. Each call goes through [`crate::fetch`], which asks
+//! [`admit`] before sending and settles the cost afterwards. A call is
+//! refused with a `request-budget` policy error (rule
+//! `request-budget-exceeded`) when the cost spent so far, plus the
+//! worst-case cost of calls still in flight, plus the worst-case cost of the
+//! next call would exceed the ceiling.
+//!
+//! The ceiling comes from `OPENAI_REQUEST_COST_CEILING_USD`, or from
+//! `max-cost-usd` in `prompt-options` for that call; without either, calls
+//! are only counted. `#[audit_wrap]` starts a fresh ledger on entry to every
+//! export call.
+//!
+//! The ceiling is independent of `OPENAI_MAX_COST_USD`, which
+//! [`CostBudgetMiddleware`](crate::middleware::CostBudgetMiddleware) checks
+//! once against the prompt's estimated cost, and of the budget of
+//! `prompt-with-budget`, which caps that call's output; a call must pass
+//! every limit that applies to it.
+
+use std::cell::RefCell;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use crate::{
+    policy::{PolicyReport, PolicyStage},
+    pricing::PricingTable,
+    tokens::count_tokens,
+    Endpoint, Usage, MODEL,
+};
+
+/// Output tokens assumed for a call that does not set `max_output_tokens`
+/// (or `max_tokens`), since such a call has no real upper bound.
+const UNCAPPED_OUTPUT_TOKENS: u32 = 4096;
+
+/// Request fields whose strings are not billed as input text.
+const UNBILLED_FIELDS: &[&str] = &["model", "type", "role", "image_url", "file_data"];
+
+/// Upstream spend of one logical request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ledger {
+    pub ceiling_usd: Option<f64>,
+    /// Cost of the calls that have completed.
+    pub spent_usd: f64,
+    /// Worst-case cost of the calls that have been admitted but not settled.
+    pub reserved_usd: f64,
+    /// Calls admitted so far.
+    pub calls: u32,
+}
+
+impl Ledger {
+    /// Admits a call that could cost up to `worst_case_usd`, reserving that
+    /// amount until it is settled.
+    pub fn admit(&mut self, worst_case_usd: f64) -> Result<(), PolicyReport> {
+        if let Some(ceiling_usd) = self.ceiling_usd {
+            let committed = self.spent_usd + self.reserved_usd;
+            if committed + worst_case_usd > ceiling_usd {
+                return Err(PolicyReport {
+                    policy: "request-budget",
+                    rules: vec!["request-budget-exceeded".to_string()],
+                    stage: PolicyStage::PreSend,
+                    detail: format!(
+                        "upstream call {} could cost up to ${worst_case_usd:.6} on top of ${committed:.6} already committed, over the ceiling of ${ceiling_usd:.6}",
+                        self.calls + 1
+                    ),
+                    remediation: "Raise OPENAI_REQUEST_COST_CEILING_USD or max-cost-usd, or lower the worst case with OPENAI_MAX_OUTPUT_TOKENS.",
+                });
+            }
+        }
+        self.reserved_usd += worst_case_usd;
+        self.calls += 1;
+        Ok(())
+    }
+
+    /// Replaces the reservation of an admitted call with its actual cost;
+    /// `None` when the call failed without being billed.
+    pub fn settle(&mut self, worst_case_usd: f64, actual_usd: Option<f64>) {
+        self.reserved_usd = (self.reserved_usd - worst_case_usd).max(0.0);
+        self.spent_usd += actual_usd.unwrap_or(0.0);
+    }
+}
+
+thread_local! {
+    static LEDGER: RefCell<Ledger> = RefCell::new(Ledger::default());
+}
+
+/// Starts the ledger of a new logical request, with no per-call ceiling.
+pub(crate) fn reset() {
+    LEDGER.with(|ledger| *ledger.borrow_mut() = Ledger::default());
+}
+
+/// The ledger of the current request.
+#[cfg(test)]
+pub(crate) fn current() -> Ledger {
+    LEDGER.with(|ledger| ledger.borrow().clone())
+}
+
+/// Sets the ceiling of the current request, overriding
+/// `OPENAI_REQUEST_COST_CEILING_USD`.
+pub(crate) fn set_ceiling(ceiling_usd: f64) -> Result<()> {
+    if !(ceiling_usd.is_finite() && ceiling_usd > 0.0) {
+        bail!("max-cost-usd must be a positive number, got {ceiling_usd}");
+    }
+    LEDGER.with(|ledger| ledger.borrow_mut().ceiling_usd = Some(ceiling_usd));
+    Ok(())
+}
+
+/// Reserves the worst-case cost of sending `json_body` to `endpoint` and
+/// returns it, or fails if the ceiling does not allow the call.
+pub(crate) fn admit(endpoint: Endpoint, json_body: &[u8]) -> Result<f64> {
+    let configured = configured_ceiling()?;
+    let body: Value = serde_json::from_slice(json_body).unwrap_or_default();
+    let worst_case_usd = worst_case_cost(endpoint, &body);
+
+    LEDGER.with(|ledger| {
+        let mut ledger = ledger.borrow_mut();
+        if ledger.ceiling_usd.is_none() {
+            ledger.ceiling_usd = configured;
+        }
+        ledger.admit(worst_case_usd).map_err(PolicyReport::reject)?;
+        Ok(worst_case_usd)
+    })
+}
+
+/// Settles an admitted call with the cost reported by `response`'s usage,
+/// or its worst case when the response does not report usage.
+pub(crate) fn settle(endpoint: Endpoint, worst_case_usd: f64, response: Option<&[u8]>) {
+    let actual_usd = response.map(|response| {
+        serde_json::from_slice::<Value>(response)
+            .ok()
+            .and_then(|response| actual_cost(endpoint, &response))
+            .unwrap_or(worst_case_usd)
+    });
+    LEDGER.with(|ledger| ledger.borrow_mut().settle(worst_case_usd, actual_usd));
+}
+
+fn configured_ceiling() -> Result<Option<f64>> {
+    let Ok(value) = std::env::var("OPENAI_REQUEST_COST_CEILING_USD") else {
+        return Ok(None);
+    };
+    match value.trim().parse::<f64>() {
+        Ok(ceiling) if ceiling.is_finite() && ceiling > 0.0 => Ok(Some(ceiling)),
+        _ => bail!("OPENAI_REQUEST_COST_CEILING_USD must be a positive number, got {value:?}"),
+    }
+}
+
+/// Cost of the estimated input tokens of `body` plus its output cap.
+/// Unpriced models are priced as the component's default model.
+fn worst_case_cost(endpoint: Endpoint, body: &Value) -> f64 {
+    let model = body["model"].as_str().unwrap_or(MODEL);
+    let input_tokens = count_tokens(&billed_text(body));
+    let output_tokens = match endpoint {
+        Endpoint::Embeddings => 0,
+        _ => ["max_output_tokens", "max_tokens"]
+            .iter()
+            .find_map(|field| body[field].as_u64())
+            .map_or(UNCAPPED_OUTPUT_TOKENS, |max| {
+                u32::try_from(max).unwrap_or(u32::MAX)
+            }),
+    };
+    pricing_for(model).cost(input_tokens, output_tokens)
+}
+
+/// Cost from the `usage` of a response (see [`Usage::parse`]).
+fn actual_cost(endpoint: Endpoint, response: &Value) -> Option<f64> {
+    let usage = Usage::parse(endpoint, response)?;
+    let model = response["model"].as_str().unwrap_or(MODEL);
+    Some(pricing_for(model).cost(usage.input_tokens, usage.output_tokens))
+}
+
+fn pricing_for(model: &str) -> &'static crate::pricing::ModelPricing {
+    let table = &PricingTable::OPENAI;
+    table
+        .lookup(model)
+        .or_else(|| table.lookup(MODEL))
+        .expect("default model is priced")
+}
+
+/// The strings of `body` that are sent to the model as input, skipping
+/// names, roles, and inline images.
+fn billed_text(body: &Value) -> String {
+    fn collect(value: &Value, text: &mut String) {
+        match value {
+            Value::String(s) => {
+                text.push_str(s);
+                text.push(' ');
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, text)),
+            Value::Object(fields) => fields
+                .iter()
+                .filter(|(name, _)| !UNBILLED_FIELDS.contains(&name.as_str()))
+                .for_each(|(_, value)| collect(value, text)),
+            _ => {}
+        }
+    }
+
+    let mut text = String::new();
+    collect(body, &mut text);
+    text
+}
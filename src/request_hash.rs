@@ -0,0 +1,218 @@
+//! Canonical request hashing.
+//!
+//! The hash covers the normalized request: the JSON body sent upstream, with
+//! the model, the final prompt after middleware, and every option set for
+//! the call. `prompt-handle` logs it for every request, and the mock provider
+//! and usage receipts use the same function. Canonical form is compact JSON with object keys in sorted order, nulls
+//! dropped (so an absent field and an explicit `null` hash the same), numbers
+//! in serde_json's shortest round-trip form, and volatile or transport-only
+//! fields removed. Changing any of these rules changes every hash, so treat
+//! them as part of the public interface.
+
+use serde_json::{Map, Value};
+
+use anyhow::Result;
+
+use crate::{
+    configure_request,
+    middleware::{self, PromptRequest},
+    parse_structured_prompt, request_body, sha256, Endpoint,
+};
+
+/// Top-level fields that never contribute to the hash.
+const EXCLUDED_FIELDS: &[&str] = &["idempotency_key", "timestamp", "request_id", "stream"];
+
+/// Canonical JSON encoding of `request`.
+pub fn canonical_json(request: &Value) -> String {
+    let request = match request {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !EXCLUDED_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        other => other.clone(),
+    };
+
+    // serde_json's `Map` is a BTreeMap, which serializes keys in sorted order.
+    serde_json::to_string(&normalize(&request)).expect("JSON values always serialize")
+}
+
+/// Hex-encoded SHA-256 of the canonical form of `request`.
+pub fn request_hash(request: &Value) -> String {
+    sha256::hex(&sha256::digest(canonical_json(request).as_bytes()))
+}
+
+/// The request `prompt-handle` sends to `endpoint` for `request`: its JSON
+/// body, plus the stop sequences the component applies itself on the
+/// Responses API, which has no `stop` field.
+pub fn normalized_request(endpoint: Endpoint, request: &PromptRequest) -> Result<Value> {
+    let mut body: Value = serde_json::from_slice(&request_body(endpoint, request)?)?;
+    if endpoint == Endpoint::Responses && !request.stop.is_empty() {
+        body["stop"] = serde_json::json!(request.stop);
+    }
+    Ok(body)
+}
+
+/// Hash of the request `prompt-handle` sends to `endpoint` for `request`.
+pub fn hash_request(endpoint: Endpoint, request: &PromptRequest) -> Result<String> {
+    normalized_request(endpoint, request).map(|request| request_hash(&request))
+}
+
+/// Hash of the request `prompt-handle` sends for `prompt`, plain text or a
+/// structured prompt. Configuration and middleware errors that would fail
+/// `prompt-handle` fail here too.
+pub fn prompt_hash(prompt: String) -> Result<String> {
+    let (prompt, overrides) = parse_structured_prompt(prompt)?;
+    let (endpoint, mut request) = configure_request(prompt, overrides)?;
+    middleware::install_from_env()?;
+    middleware::apply_request(&mut request)?;
+    hash_request(endpoint, &request)
+}
+
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), normalize(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> PromptRequest {
+        PromptRequest {
+            model: "gpt-4.1".to_string(),
+            input: "Hello".to_string(),
+            instructions: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            stop: Vec::new(),
+        }
+    }
+
+    fn configured() -> PromptRequest {
+        PromptRequest {
+            instructions: Some("Be brief.".to_string()),
+            temperature: Some(0.7),
+            top_p: Some(0.9),
+            max_output_tokens: Some(256),
+            stop: vec!["END".to_string()],
+            ..request()
+        }
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys_drops_nulls_and_volatile_fields() {
+        let request = json!({
+            "stream": true,
+            "model": "gpt-4.1",
+            "timestamp": 1700000000,
+            "input": "Hi",
+            "options": {"z": 1, "a": null, "m": [1.0, 0.1, 2, {"b": 2, "a": 1}]},
+            "idempotency_key": "abc",
+            "request_id": "r1",
+            "instructions": null
+        });
+        assert_eq!(
+            canonical_json(&request),
+            r#"{"input":"Hi","model":"gpt-4.1","options":{"m":[1.0,0.1,2,{"a":1,"b":2}],"z":1}}"#
+        );
+    }
+
+    #[test]
+    fn golden_hash_ignores_key_order_nulls_and_volatile_fields() {
+        const GOLDEN: &str = "43682916e5cc5ad781844c92b4a5a9561cc5ba0b30af5c62c4b1c1bd6bccd592";
+        assert_eq!(
+            request_hash(&json!({"model": "gpt-4.1", "input": "Hello"})),
+            GOLDEN
+        );
+        assert_eq!(
+            request_hash(
+                &json!({"input": "Hello", "stream": false, "top_p": null, "model": "gpt-4.1"})
+            ),
+            GOLDEN
+        );
+        assert_eq!(
+            hash_request(Endpoint::Responses, &request()).unwrap(),
+            GOLDEN
+        );
+    }
+
+    #[test]
+    fn golden_hashes_of_configured_requests() {
+        let responses = normalized_request(Endpoint::Responses, &configured()).unwrap();
+        assert_eq!(
+            canonical_json(&responses),
+            r#"{"input":"Hello","instructions":"Be brief.","max_output_tokens":256,"model":"gpt-4.1","stop":["END"],"temperature":0.7,"top_p":0.9}"#
+        );
+        assert_eq!(
+            request_hash(&responses),
+            "093027befd6db319cbe09c12b291c8bea481b1a2d9392471564514bd55c54c26"
+        );
+
+        let chat = normalized_request(Endpoint::ChatCompletions, &configured()).unwrap();
+        assert_eq!(
+            canonical_json(&chat),
+            r#"{"max_tokens":256,"messages":[{"content":"Be brief.","role":"system"},{"content":"Hello","role":"user"}],"model":"gpt-4.1","stop":["END"],"temperature":0.7,"top_p":0.9}"#
+        );
+        assert_eq!(
+            request_hash(&chat),
+            "2a24b8a4b15bf7c8a2da23c12849184b0afa7d7b487b339436a854ef98f4cb40"
+        );
+    }
+
+    #[test]
+    fn every_option_changes_the_hash() {
+        let base = hash_request(Endpoint::Responses, &configured()).unwrap();
+        for changed in [
+            PromptRequest {
+                model: "gpt-4o".to_string(),
+                ..configured()
+            },
+            PromptRequest {
+                input: "Hello!".to_string(),
+                ..configured()
+            },
+            PromptRequest {
+                instructions: None,
+                ..configured()
+            },
+            PromptRequest {
+                temperature: Some(0.8),
+                ..configured()
+            },
+            PromptRequest {
+                top_p: None,
+                ..configured()
+            },
+            PromptRequest {
+                max_output_tokens: Some(255),
+                ..configured()
+            },
+            PromptRequest {
+                stop: vec!["---".to_string()],
+                ..configured()
+            },
+        ] {
+            let hash = hash_request(Endpoint::Responses, &changed).unwrap();
+            assert_ne!(hash, base, "{changed:?}");
+        }
+        assert_ne!(
+            hash_request(Endpoint::ChatCompletions, &configured()).unwrap(),
+            base
+        );
+    }
+}
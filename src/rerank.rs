@@ -0,0 +1,104 @@
+//! Model-scored document reranking, a cheaper alternative to a cross-encoder.
+
+use anyhow::{anyhow, ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug)]
+pub struct RerankResult {
+    pub index: usize,
+    pub score: f32,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+struct Scores {
+    scores: Vec<DocumentScore>,
+}
+
+#[derive(Deserialize)]
+struct DocumentScore {
+    index: usize,
+    score: f32,
+}
+
+const INSTRUCTIONS: &str = "You are a search relevance judge. \
+Score how well each numbered document answers the query on a scale from 0 (irrelevant) to 10 (directly answers it). \
+Judge every document independently and return exactly one score per document index.";
+
+/// Scores every document against `query` in a single model call and returns
+/// the `top_k` highest-scoring ones, best first.
+pub async fn rerank(
+    query: String,
+    documents: Vec<String>,
+    top_k: usize,
+) -> Result<Vec<RerankResult>> {
+    if documents.is_empty() || top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "scores": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "score": { "type": "number", "minimum": 0, "maximum": 10 }
+                    },
+                    "required": ["index", "score"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["scores"],
+        "additionalProperties": false
+    });
+
+    let mut input = format!("Query: {query}\n\nDocuments:\n");
+    for (index, document) in documents.iter().enumerate() {
+        input.push_str(&format!("[{index}] {document}\n"));
+    }
+    let response: Scores =
+        structured::request("rerank_scores", INSTRUCTIONS, &input, schema).await?;
+
+    let mut scores = vec![None; documents.len()];
+    for DocumentScore { index, score } in response.scores {
+        ensure!(
+            index < documents.len(),
+            "model scored unknown document {index}"
+        );
+        scores[index].get_or_insert(score.clamp(0.0, 10.0));
+    }
+
+    let mut results = documents
+        .into_iter()
+        .zip(scores)
+        .enumerate()
+        .map(|(index, (text, score))| {
+            score
+                .map(|score| RerankResult { index, score, text })
+                .ok_or_else(|| anyhow!("model did not score document {index}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Stable sort keeps the original order among equal scores.
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(top_k);
+
+    Ok(results)
+}
+
+impl From<RerankResult> for wit::RerankResult {
+    fn from(result: RerankResult) -> Self {
+        Self {
+            index: u32::try_from(result.index).unwrap_or(u32::MAX),
+            score: result.score,
+            text: result.text,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Soft-fail: a configured fallback answer instead of an error when the
+//! provider cannot be reached.
+//!
+//! With `OPENAI_SOFT_FAIL=true`, a `prompt-handle` request that cannot get
+//! an answer from the provider returns the text of `OPENAI_SOFT_FAIL_TEXT`
+//! verbatim, marked as degraded for `prompt-handle-result` callers. The
+//! failure is still logged. Only transport and provider failures are
+//! softened: 5xx statuses, 429, and I/O errors including timeouts.
+//! Configuration errors, a missing API key, other 4xx statuses, and policy
+//! rejections (such as the cost budget) are returned as they are, since
+//! retrying cannot fix them and a broken deployment must not look merely
+//! degraded. Both
+//! variables are validated when the request is configured, so a missing
+//! fallback text fails every request rather than only failing ones.
+
+use anyhow::{bail, Result};
+
+use crate::error::OpenAiError;
+
+/// The fallback answer when soft-fail is enabled, read through `var`.
+pub(crate) fn configured(var: impl Fn(&str) -> Option<String>) -> Result<Option<String>> {
+    match var("OPENAI_SOFT_FAIL").as_deref() {
+        None | Some("false") => return Ok(None),
+        Some("true") => {}
+        Some(other) => bail!("OPENAI_SOFT_FAIL must be true or false, got {other:?}"),
+    }
+    match var("OPENAI_SOFT_FAIL_TEXT") {
+        Some(text) if !text.trim().is_empty() => Ok(Some(text)),
+        _ => bail!("OPENAI_SOFT_FAIL_TEXT must be set when OPENAI_SOFT_FAIL=true"),
+    }
+}
+
+/// The configured `fallback` answer for `error`, if soft-fail applies to it.
+pub(crate) fn fallback_for(error: &anyhow::Error, fallback: Option<&str>) -> Option<String> {
+    let fallback = fallback?;
+    let softened = match error.downcast_ref::<OpenAiError>()? {
+        OpenAiError::HttpError { status, .. } => *status >= 500,
+        OpenAiError::RateLimited { .. } | OpenAiError::IoError(_) => true,
+        OpenAiError::MissingApiKey | OpenAiError::ParseError(_) => false,
+    };
+    if !softened {
+        return None;
+    }
+
+    eprintln!("[COMPONENT] Soft-failing with the fallback answer after: {error:#}");
+    Some(fallback.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{parse_base_url, request_budget::Ledger};
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn requires_the_fallback_text_when_enabled() {
+        assert_eq!(configured(vars(&[])).unwrap(), None);
+        assert_eq!(
+            configured(vars(&[
+                ("OPENAI_SOFT_FAIL", "false"),
+                ("OPENAI_SOFT_FAIL_TEXT", "x")
+            ]))
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            configured(vars(&[
+                ("OPENAI_SOFT_FAIL", "true"),
+                ("OPENAI_SOFT_FAIL_TEXT", "Try again later."),
+            ]))
+            .unwrap()
+            .as_deref(),
+            Some("Try again later.")
+        );
+
+        for text in [None, Some(""), Some("  ")] {
+            let mut pairs = vec![("OPENAI_SOFT_FAIL", "true")];
+            pairs.extend(text.map(|text| ("OPENAI_SOFT_FAIL_TEXT", text)));
+            let error = configured(vars(&pairs)).unwrap_err();
+            assert!(
+                error.to_string().contains("OPENAI_SOFT_FAIL_TEXT"),
+                "{error}"
+            );
+        }
+        assert!(configured(vars(&[("OPENAI_SOFT_FAIL", "yes")])).is_err());
+    }
+
+    #[test]
+    fn softens_transport_failures_only() {
+        let fallback = Some("Try again later.");
+        for error in [
+            OpenAiError::RateLimited {
+                retry_after_secs: Some(3),
+            },
+            OpenAiError::HttpError {
+                status: 503,
+                body: String::new(),
+            },
+            OpenAiError::IoError("connection reset".to_string()),
+        ] {
+            let error = anyhow::Error::from(error);
+            assert_eq!(fallback_for(&error, fallback).as_deref(), fallback);
+            assert_eq!(fallback_for(&error, None), None);
+        }
+
+        let refusal = Ledger {
+            ceiling_usd: Some(0.001),
+            ..Ledger::default()
+        }
+        .admit(1.0)
+        .unwrap_err()
+        .reject();
+        assert_eq!(fallback_for(&refusal, fallback), None);
+    }
+
+    #[test]
+    fn configuration_and_client_errors_are_not_softened() {
+        let fallback = Some("Try again later.");
+        for error in [
+            parse_base_url("not a url").unwrap_err(),
+            parse_base_url("ftp://api.openai.com").unwrap_err(),
+            OpenAiError::MissingApiKey.into(),
+            OpenAiError::HttpError {
+                status: 401,
+                body: "invalid api key".to_string(),
+            }
+            .into(),
+            OpenAiError::ParseError("No output text found in response".to_string()).into(),
+            anyhow::anyhow!("unsupported AI_PROVIDER \"other\", expected `openai` or `mock`"),
+        ] {
+            assert_eq!(fallback_for(&error, fallback), None, "{error}");
+        }
+    }
+}
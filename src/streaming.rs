@@ -0,0 +1,381 @@
+//! Streaming prompts: the request is sent with `"stream": true` and the
+//! answer is collected as the text deltas of the server-sent event stream.
+//!
+//! Events are parsed as body chunks arrive. A line (or a multi-byte
+//! character) may be split across reads, so [`SseParser`] buffers partial
+//! lines between chunks. Both the Responses API's
+//! `response.output_text.delta` events and Chat Completions'
+//! `choices[0].delta.content` are understood; the stream ends at
+//! `response.completed` or a `[DONE]` sentinel, and empty `data:`
+//! keep-alives are skipped. A stream cut off at the output token limit
+//! (`response.incomplete` for `max_output_tokens`, or a Chat
+//! `finish_reason` of `length`) ends with the truncation marker as its last
+//! delta; any other `response.incomplete` is an error. The request is the one `prompt-handle` would
+//! send, request middleware included, with `stream` set; response
+//! middleware does not run on streamed answers.
+//!
+//! The request goes through [`crate::fetch_stream`], so chaos faults and the
+//! request's cost ceiling apply as they do to other calls; the call is
+//! charged from the usage in `response.completed` or `response.incomplete`.
+
+use std::ops::ControlFlow;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::{
+    configure_request, fetch_stream, middleware, request_body, truncation_marker, Endpoint,
+    Overrides,
+};
+
+/// Splits a byte stream into the `data` payloads of its SSE events.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// Bytes after the last complete line.
+    partial: Vec<u8>,
+    /// `data` lines of the event being read.
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Consumes `chunk` and returns the payloads of the events it completes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(newline) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.line(line.trim_end_matches(['\n', '\r']), &mut events);
+        }
+        events
+    }
+
+    /// Returns the payload of an event left unterminated at end of stream.
+    pub fn finish(mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        let rest = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).into_owned();
+        if !rest.is_empty() {
+            self.line(rest.trim_end_matches('\r'), &mut events);
+        }
+        self.line("", &mut events);
+        events
+    }
+
+    fn line(&mut self, line: &str, events: &mut Vec<String>) {
+        if line.is_empty() {
+            if !self.data.is_empty() {
+                events.push(self.data.join("\n"));
+                self.data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        // `event:`, `id:`, `retry:` and `:` comment lines carry nothing the
+        // payload does not.
+    }
+}
+
+/// What one event payload means for the stream.
+pub(crate) enum Event {
+    Delta(String),
+    /// The end of the stream, with the final response object if the event
+    /// carries one.
+    Done(Option<Value>),
+    /// The model stopped at the output token limit; the stream ends here.
+    Truncated(Option<Value>),
+    Skip,
+}
+
+pub(crate) fn interpret(payload: &str) -> Result<Event> {
+    let payload = payload.trim();
+    if payload.is_empty() {
+        return Ok(Event::Skip);
+    }
+    if payload == "[DONE]" {
+        return Ok(Event::Done(None));
+    }
+
+    let event: Value =
+        serde_json::from_str(payload).with_context(|| format!("invalid SSE event {payload:?}"))?;
+    match event["type"].as_str() {
+        Some("response.output_text.delta") => Ok(Event::Delta(
+            event["delta"].as_str().unwrap_or_default().to_string(),
+        )),
+        Some("response.completed") => Ok(Event::Done(event.get("response").cloned())),
+        Some("response.incomplete") => {
+            let reason = event
+                .pointer("/response/incomplete_details/reason")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown");
+            if reason != "max_output_tokens" {
+                bail!("stream incomplete: {reason}")
+            }
+            Ok(Event::Truncated(event.get("response").cloned()))
+        }
+        Some("response.failed" | "error") => {
+            let message = event
+                .pointer("/response/error/message")
+                .or_else(|| event.get("message"))
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            bail!("stream failed: {message}")
+        }
+        Some(_) => Ok(Event::Skip),
+        None => {
+            if let Some(content) = event
+                .pointer("/choices/0/delta/content")
+                .and_then(Value::as_str)
+                .filter(|content| !content.is_empty())
+            {
+                return Ok(Event::Delta(content.to_string()));
+            }
+            Ok(
+                match event
+                    .pointer("/choices/0/finish_reason")
+                    .and_then(Value::as_str)
+                {
+                    Some("length") => Event::Truncated(None),
+                    _ => Event::Skip,
+                },
+            )
+        }
+    }
+}
+
+/// The request `prompt-handle` would send for `prompt` (configured model,
+/// endpoint, instructions, sampling, output cap, and request middleware),
+/// with `stream` set.
+fn stream_request(prompt: String) -> Result<(Endpoint, Vec<u8>)> {
+    let (endpoint, mut request) = configure_request(prompt, Overrides::default())?;
+    middleware::install_from_env()?;
+    middleware::apply_request(&mut request)?;
+    let mut body: Value = serde_json::from_slice(&request_body(endpoint, &request)?)?;
+    body["stream"] = Value::Bool(true);
+    Ok((endpoint, serde_json::to_vec(&body)?))
+}
+
+/// Sends `prompt` as a streaming request and returns the text deltas in the
+/// order they arrived. Empty deltas are dropped.
+pub async fn prompt_stream(prompt: String) -> Result<Vec<String>> {
+    let (endpoint, body) = stream_request(prompt)?;
+    eprintln!("[COMPONENT] Streaming via {}", endpoint.path());
+
+    let mut parser = SseParser::default();
+    let mut deltas = Vec::new();
+    fetch_stream(endpoint, body, |chunk| {
+        let payloads = match chunk {
+            Some(chunk) => parser.feed(chunk),
+            None => std::mem::take(&mut parser).finish(),
+        };
+        for payload in payloads {
+            match interpret(&payload)? {
+                Event::Delta(delta) if !delta.is_empty() => deltas.push(delta),
+                Event::Delta(_) | Event::Skip => {}
+                Event::Done(response) => {
+                    let response = response.map(|response| response.to_string().into_bytes());
+                    return Ok(ControlFlow::Break(response));
+                }
+                // Marked like a truncated `prompt-handle` answer.
+                Event::Truncated(response) => {
+                    eprintln!("[COMPONENT] Stream truncated at the output token limit");
+                    let marker = truncation_marker();
+                    if !marker.is_empty() {
+                        deltas.push(marker);
+                    }
+                    let response = response.map(|response| response.to_string().into_bytes());
+                    return Ok(ControlFlow::Break(response));
+                }
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    })
+    .await?;
+
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor;
+
+    use super::*;
+    use crate::{policy::PolicyReport, request_budget};
+
+    /// Feeds `stream` split at each of `cuts` and returns every payload.
+    fn parse(stream: &[u8], cuts: &[usize]) -> Vec<String> {
+        let mut parser = SseParser::default();
+        let mut payloads = Vec::new();
+        let mut start = 0;
+        for &cut in cuts.iter().chain([&stream.len()]) {
+            payloads.extend(parser.feed(&stream[start..cut]));
+            start = cut;
+        }
+        payloads.extend(parser.finish());
+        payloads
+    }
+
+    fn deltas(payloads: &[String]) -> Vec<String> {
+        payloads
+            .iter()
+            .filter_map(|payload| match interpret(payload).unwrap() {
+                Event::Delta(delta) => Some(delta),
+                Event::Done(_) | Event::Truncated(_) | Event::Skip => None,
+            })
+            .collect()
+    }
+
+    const STREAM: &str = concat!(
+        "event: response.output_text.delta\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"Grüße, \"}\n",
+        "\n",
+        ": keep-alive\n",
+        "data:\n",
+        "\n",
+        "event: response.output_text.delta\r\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"日本\"}\r\n",
+        "\r\n",
+        "data: {\"type\":\"response.completed\"}\n",
+        "\n",
+    );
+
+    #[test]
+    fn splits_events_at_blank_lines() {
+        let payloads = parse(STREAM.as_bytes(), &[]);
+        assert_eq!(payloads.len(), 4, "{payloads:?}");
+        assert_eq!(deltas(&payloads), ["Grüße, ", "日本"]);
+        // The empty `data:` keep-alive is an empty payload, skipped later.
+        assert!(matches!(interpret(&payloads[1]).unwrap(), Event::Skip));
+        assert!(matches!(interpret(&payloads[3]).unwrap(), Event::Done(_)));
+    }
+
+    #[test]
+    fn every_chunk_boundary_gives_the_same_events() {
+        let stream = STREAM.as_bytes();
+        let whole = parse(stream, &[]);
+        for cut in 1..stream.len() {
+            assert_eq!(parse(stream, &[cut]), whole, "split at byte {cut}");
+        }
+        let bytewise: Vec<usize> = (1..stream.len()).collect();
+        assert_eq!(parse(stream, &bytewise), whole);
+    }
+
+    #[test]
+    fn splits_inside_a_code_point_are_reassembled() {
+        let stream = STREAM.as_bytes();
+        let umlaut = STREAM.find('ü').unwrap();
+        let kanji = STREAM.find('日').unwrap();
+        let payloads = parse(stream, &[umlaut + 1, kanji + 1, kanji + 2]);
+        assert_eq!(deltas(&payloads), ["Grüße, ", "日本"]);
+        assert!(payloads.iter().all(|payload| !payload.contains('\u{FFFD}')));
+    }
+
+    #[test]
+    fn splits_inside_an_event_wait_for_its_end() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"data: {\"type\":\"response.out").is_empty());
+        assert!(parser
+            .feed(b"put_text.delta\",\"delta\":\"Hi\"}\n")
+            .is_empty());
+        let payloads = parser.feed(b"\n");
+        assert_eq!(deltas(&payloads), ["Hi"]);
+    }
+
+    #[test]
+    fn escaped_newlines_quotes_and_backslashes_survive() {
+        let event = r#"data: {"type":"response.output_text.delta","delta":"a\nb \"q\" c\\d"}"#;
+        let stream = format!("{event}\n\n");
+        let stream = stream.as_bytes();
+        for cut in 1..stream.len() {
+            assert_eq!(deltas(&parse(stream, &[cut])), ["a\nb \"q\" c\\d"]);
+        }
+    }
+
+    #[test]
+    fn multi_line_data_is_joined_with_newlines() {
+        let payloads = parse(b"data: first\ndata: second\n\n", &[]);
+        assert_eq!(payloads, ["first\nsecond"]);
+    }
+
+    #[test]
+    fn an_unterminated_event_is_returned_at_finish() {
+        let mut parser = SseParser::default();
+        assert!(parser
+            .feed(b"data: {\"choices\":[{\"delta\":{\"content\":\"x\"}}]}")
+            .is_empty());
+        assert_eq!(deltas(&parser.finish()), ["x"]);
+    }
+
+    #[test]
+    fn chat_completions_deltas_and_done() {
+        let stream = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let payloads = parse(stream.as_bytes(), &[]);
+        assert_eq!(deltas(&payloads), ["Hel", "lo"]);
+        assert!(matches!(interpret(&payloads[3]).unwrap(), Event::Done(_)));
+    }
+
+    #[test]
+    fn streams_send_the_configured_request() {
+        // No other test depends on the default model.
+        std::env::set_var("OPENAI_MODEL", "gpt-4.1-mini");
+        let (endpoint, body) = stream_request("Hello".to_string()).unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(endpoint, Endpoint::Responses);
+        assert_eq!(body["model"], "gpt-4.1-mini");
+        assert_eq!(body["input"], "Hello");
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn streams_are_admitted_and_settled_from_their_usage() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        let deltas = executor::block_on(prompt_stream("one two".to_string())).unwrap();
+        assert!(!deltas.is_empty());
+        assert!(deltas.concat().contains("one two"), "{deltas:?}");
+        let ledger = request_budget::current();
+        assert_eq!(ledger.calls, 1);
+        assert_eq!(ledger.reserved_usd, 0.0);
+        assert!(ledger.spent_usd > 0.0, "{ledger:?}");
+    }
+
+    #[test]
+    fn streams_count_against_the_request_ceiling() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        request_budget::set_ceiling(1e-12).unwrap();
+        let error = executor::block_on(prompt_stream("Hello".to_string())).unwrap_err();
+        let report = error.downcast_ref::<PolicyReport>().unwrap();
+        assert_eq!(report.rules, ["request-budget-exceeded"]);
+        assert_eq!(request_budget::current().calls, 0);
+    }
+
+    #[test]
+    fn failed_streams_are_errors() {
+        let failed = r#"{"type":"response.failed","response":{"error":{"message":"overloaded"}}}"#;
+        let error = interpret(failed).err().unwrap();
+        assert_eq!(error.to_string(), "stream failed: overloaded");
+        assert!(interpret("{not json").is_err());
+    }
+
+    #[test]
+    fn incomplete_streams_are_truncated_or_errors() {
+        let capped = r#"{"type":"response.incomplete","response":{"incomplete_details":{"reason":"max_output_tokens"},"usage":{"input_tokens":3,"output_tokens":16}}}"#;
+        let Event::Truncated(Some(response)) = interpret(capped).unwrap() else {
+            panic!("expected a truncated stream");
+        };
+        assert_eq!(response["usage"]["output_tokens"], 16);
+
+        let filtered = r#"{"type":"response.incomplete","response":{"incomplete_details":{"reason":"content_filter"}}}"#;
+        let error = interpret(filtered).err().unwrap();
+        assert_eq!(error.to_string(), "stream incomplete: content_filter");
+
+        let chat = r#"{"choices":[{"delta":{},"finish_reason":"length"}]}"#;
+        assert!(matches!(interpret(chat).unwrap(), Event::Truncated(None)));
+    }
+}
@@ -0,0 +1,68 @@
+//! Structured output: requests a reply that conforms to a JSON schema and
+//! deserializes it into a typed value.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{fetch, parse_complete_response, Endpoint, MODEL};
+
+/// Sends `input` with `instructions` to the Responses API, constraining the
+/// output to `schema` (strict mode), and parses the returned JSON as `T`.
+pub(crate) async fn request<T: DeserializeOwned>(
+    name: &str,
+    instructions: &str,
+    input: &str,
+    schema: Value,
+) -> Result<T> {
+    request_with_model(MODEL, name, instructions, input, schema).await
+}
+
+/// [`request`] against `model` instead of the default model.
+pub(crate) async fn request_with_model<T: DeserializeOwned>(
+    model: &str,
+    name: &str,
+    instructions: &str,
+    input: &str,
+    schema: Value,
+) -> Result<T> {
+    let body = request_body(model, name, instructions, input, schema);
+
+    eprintln!("[COMPONENT] Structured request: {name}");
+
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+    parse_output(name, &raw_response)
+}
+
+/// The Responses API body of a [`request_with_model`] call.
+pub(crate) fn request_body(
+    model: &str,
+    name: &str,
+    instructions: &str,
+    input: &str,
+    schema: Value,
+) -> Value {
+    json!({
+        "model": model,
+        "instructions": instructions,
+        "input": input,
+        "text": {
+            "format": {
+                "type": "json_schema",
+                "name": name,
+                "schema": schema,
+                "strict": true,
+            }
+        },
+        "stream": false,
+    })
+}
+
+/// Parses the answer of `raw_response` as the `name` schema's `T`.
+pub(crate) fn parse_output<T: DeserializeOwned>(name: &str, raw_response: &str) -> Result<T> {
+    let text = parse_complete_response(raw_response)?.text;
+    serde_json::from_str(&text)
+        .with_context(|| format!("model output does not match the `{name}` schema"))
+}
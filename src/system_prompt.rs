@@ -0,0 +1,102 @@
+//! Meta-prompted generation of system prompts for AI applications.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::structured;
+
+/// Clarity below this triggers one revision of the generated prompt.
+const MIN_CLARITY: f32 = 0.7;
+
+#[derive(Deserialize)]
+struct GeneratedPrompt {
+    system_prompt: String,
+}
+
+#[derive(Deserialize)]
+struct PromptEvaluation {
+    clarity_score: f32,
+    issues: Vec<String>,
+}
+
+const INSTRUCTIONS: &str = "You are a prompt engineer writing system prompts for production AI applications. \
+Write a system prompt for the described role that states the role and goal, every constraint as an explicit rule, \
+how to handle ambiguous or out-of-scope requests, and the expected output format. \
+Use the examples only as guidance for the format and tone of answers; do not copy them into the prompt verbatim.";
+
+const EVALUATION_INSTRUCTIONS: &str = "You review system prompts. \
+Rate how clear and unambiguous the prompt is for a model to follow, from 0 (unusable) to 1 (perfectly clear), \
+and list concrete issues: vague wording, contradictory or missing rules, and constraints that are not covered.";
+
+/// Generates a system prompt for `role` honoring `constraints`, using
+/// `examples` (input, ideal output) as formatting guidance. The prompt is
+/// rated for clarity and revised once if it scores below 0.7.
+pub async fn generate_system_prompt(
+    role: String,
+    constraints: Vec<String>,
+    examples: Vec<(String, String)>,
+) -> Result<String> {
+    ensure!(!role.trim().is_empty(), "role must not be empty");
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "system_prompt": { "type": "string" }
+        },
+        "required": ["system_prompt"],
+        "additionalProperties": false
+    });
+
+    let constraints = constraints
+        .iter()
+        .map(|constraint| format!("- {constraint}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let examples = examples
+        .iter()
+        .enumerate()
+        .map(|(index, (input, output))| {
+            format!("Example {}\nInput: {input}\nOutput: {output}", index + 1)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let brief = format!("Role: {role}\n\nConstraints:\n{constraints}\n\nExamples:\n{examples}");
+
+    let generated: GeneratedPrompt =
+        structured::request("system_prompt", INSTRUCTIONS, &brief, schema.clone()).await?;
+    let evaluation = evaluate(&generated.system_prompt, &brief).await?;
+    if evaluation.clarity_score >= MIN_CLARITY {
+        return Ok(generated.system_prompt);
+    }
+
+    eprintln!(
+        "[COMPONENT] System prompt clarity {:.2} is below {MIN_CLARITY}, revising",
+        evaluation.clarity_score
+    );
+    let input = format!(
+        "{brief}\n\nRevise this system prompt to fix the issues listed.\n\nPrompt:\n{}\n\nIssues:\n{}",
+        generated.system_prompt,
+        evaluation.issues.join("\n")
+    );
+    let revised: GeneratedPrompt =
+        structured::request("system_prompt", INSTRUCTIONS, &input, schema).await?;
+
+    Ok(revised.system_prompt)
+}
+
+async fn evaluate(system_prompt: &str, brief: &str) -> Result<PromptEvaluation> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "clarity_score": { "type": "number", "minimum": 0, "maximum": 1 },
+            "issues": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["clarity_score", "issues"],
+        "additionalProperties": false
+    });
+
+    let input =
+        format!("Brief the prompt was written for:\n{brief}\n\nSystem prompt:\n{system_prompt}");
+    structured::request("prompt_evaluation", EVALUATION_INSTRUCTIONS, &input, schema).await
+}
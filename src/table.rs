@@ -0,0 +1,57 @@
+//! Parsing prose, Markdown, and delimited tables into rows and columns.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+#[derive(Debug, Deserialize)]
+pub struct TableData {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+const INSTRUCTIONS: &str = "You extract tables from text. \
+The input may be a Markdown table, pipe- or comma-separated values, or a natural-language description of tabular data. \
+Return the column headers and every row as strings, in the original order, with exactly one cell per header in each row. \
+Use an empty string for missing cells and invent short descriptive headers only if the input has none.";
+
+/// Asks the model to extract the table described by `text`.
+pub async fn parse_table(text: String) -> Result<TableData> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "headers": { "type": "array", "items": { "type": "string" } },
+            "rows": {
+                "type": "array",
+                "items": { "type": "array", "items": { "type": "string" } }
+            }
+        },
+        "required": ["headers", "rows"],
+        "additionalProperties": false
+    });
+
+    let table: TableData = structured::request("table_data", INSTRUCTIONS, &text, schema).await?;
+
+    ensure!(!table.headers.is_empty(), "table has no headers");
+    for (index, row) in table.rows.iter().enumerate() {
+        ensure!(
+            row.len() == table.headers.len(),
+            "row {index} has {} columns, expected {}",
+            row.len(),
+            table.headers.len()
+        );
+    }
+
+    Ok(table)
+}
+
+impl From<TableData> for wit::TableData {
+    fn from(table: TableData) -> Self {
+        Self {
+            headers: table.headers,
+            rows: table.rows,
+        }
+    }
+}
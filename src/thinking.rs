@@ -0,0 +1,189 @@
+//! Prompts that return the model's reasoning alongside its answer.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{
+    bindings::exports::wasmcloud::ai::response_handler as wit, configured_model, fetch,
+    parse_complete_response, Endpoint,
+};
+
+#[derive(Debug)]
+pub struct ThinkingResponse {
+    pub reasoning: String,
+    pub answer: String,
+}
+
+const INSTRUCTIONS: &str = "Before answering, think through the problem step by step inside \
+<thinking></thinking> tags. After the closing tag, give only the final answer.";
+
+/// Sends `prompt` to the configured model (`OPENAI_MODEL`) and splits the
+/// reply into reasoning and answer.
+///
+/// Reasoning models (`o1`, `o3`, `o4` families) reason natively, so their
+/// reasoning summary is requested and read from the response's reasoning
+/// items; other models are asked to write their reasoning in a `<thinking>`
+/// tag.
+pub async fn prompt_with_thinking(prompt: String) -> Result<ThinkingResponse> {
+    let model = configured_model()?;
+    let native = is_reasoning_model(&model);
+    eprintln!(
+        "[COMPONENT] Thinking with {model} ({})",
+        if native {
+            "native reasoning"
+        } else {
+            "<thinking> tag"
+        }
+    );
+    let body = if native {
+        json!({
+            "model": model,
+            "input": prompt,
+            "reasoning": { "summary": "auto" },
+            "stream": false,
+        })
+    } else {
+        json!({
+            "model": model,
+            "instructions": INSTRUCTIONS,
+            "input": prompt,
+            "stream": false,
+        })
+    };
+
+    let raw_response =
+        String::from_utf8(fetch(Endpoint::Responses, serde_json::to_vec(&body)?).await?)
+            .map_err(|_| anyhow!("Invalid UTF-8 response"))?;
+
+    if native {
+        parse_native(&raw_response)
+    } else {
+        Ok(split_thinking(
+            &parse_complete_response(&raw_response)?.text,
+        ))
+    }
+}
+
+fn is_reasoning_model(model: &str) -> bool {
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|family| model == *family || model.starts_with(&format!("{family}-")))
+}
+
+/// Reads the reasoning summary and the answer from a reasoning model's
+/// output items, which put a `reasoning` item before the `message`.
+fn parse_native(raw_response: &str) -> Result<ThinkingResponse> {
+    let json: Value = serde_json::from_str(raw_response).context("Failed to parse JSON")?;
+    let items = json["output"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let texts = |item_type: &str, field: &str| -> Vec<String> {
+        items
+            .iter()
+            .filter(|item| item["type"] == item_type)
+            .flat_map(|item| item[field].as_array().cloned().unwrap_or_default())
+            .filter_map(|part| part["text"].as_str().map(str::to_string))
+            .collect()
+    };
+
+    let answer = texts("message", "content").join("");
+    if answer.is_empty() {
+        return Err(anyhow!("No output text found in response"));
+    }
+
+    Ok(ThinkingResponse {
+        reasoning: texts("reasoning", "summary").join("\n\n"),
+        answer,
+    })
+}
+
+/// Separates a `<thinking>` block from the rest of the text. Text without
+/// the tag is all answer.
+fn split_thinking(text: &str) -> ThinkingResponse {
+    const OPEN: &str = "<thinking>";
+    const CLOSE: &str = "</thinking>";
+
+    let Some(start) = text.find(OPEN) else {
+        return ThinkingResponse {
+            reasoning: String::new(),
+            answer: text.trim().to_string(),
+        };
+    };
+    let inner_start = start + OPEN.len();
+    // An unclosed tag means the answer never arrived; everything is reasoning.
+    let inner_end = text[inner_start..]
+        .find(CLOSE)
+        .map_or(text.len(), |offset| inner_start + offset);
+    let after = (inner_end + CLOSE.len()).min(text.len());
+
+    ThinkingResponse {
+        reasoning: text[inner_start..inner_end].trim().to_string(),
+        answer: format!("{}{}", &text[..start], &text[after..])
+            .trim()
+            .to_string(),
+    }
+}
+
+impl From<ThinkingResponse> for wit::ThinkingResponse {
+    fn from(response: ThinkingResponse) -> Self {
+        Self {
+            reasoning: response.reasoning,
+            answer: response.answer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_reasoning_model_families() {
+        for model in ["o1", "o1-preview", "o3", "o3-mini", "o4-mini-2025-04-16"] {
+            assert!(is_reasoning_model(model), "{model}");
+        }
+        for model in ["gpt-4.1", "gpt-4o", "o10", "o3mini", "go3-mini"] {
+            assert!(!is_reasoning_model(model), "{model}");
+        }
+    }
+
+    #[test]
+    fn reads_native_reasoning_summaries() {
+        let response = r#"{"output": [
+            {"type": "reasoning", "summary": [
+                {"type": "summary_text", "text": "Step one."},
+                {"type": "summary_text", "text": "Step two."}
+            ]},
+            {"type": "message", "content": [{"type": "output_text", "text": "42"}]}
+        ]}"#;
+        let parsed = parse_native(response).unwrap();
+        assert_eq!(parsed.reasoning, "Step one.\n\nStep two.");
+        assert_eq!(parsed.answer, "42");
+
+        let no_answer = r#"{"output": [{"type": "reasoning", "summary": []}]}"#;
+        assert!(parse_native(no_answer).is_err());
+    }
+
+    #[test]
+    fn splits_thinking_tags_from_the_answer() {
+        let split = split_thinking("<thinking> 6 times 7 </thinking>\n42");
+        assert_eq!(
+            (split.reasoning.as_str(), split.answer.as_str()),
+            ("6 times 7", "42")
+        );
+
+        let untagged = split_thinking("  just 42 ");
+        assert_eq!(
+            (untagged.reasoning.as_str(), untagged.answer.as_str()),
+            ("", "just 42")
+        );
+
+        let unclosed = split_thinking("Sure. <thinking>still going");
+        assert_eq!(
+            (unclosed.reasoning.as_str(), unclosed.answer.as_str()),
+            ("still going", "Sure.")
+        );
+    }
+}
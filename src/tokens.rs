@@ -0,0 +1,12 @@
+//! Token count estimation.
+
+/// Rough number of characters per token for English text with OpenAI's
+/// tokenizers.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates how many tokens `text` occupies. This is a character-count
+/// heuristic, not a tokenizer, so treat it as an approximation.
+pub fn count_tokens(text: &str) -> u32 {
+    let chars = text.chars().count();
+    u32::try_from(chars.div_ceil(CHARS_PER_TOKEN)).unwrap_or(u32::MAX)
+}
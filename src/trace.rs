@@ -0,0 +1,172 @@
+//! Per-request trace of the `prompt-handle` pipeline, for debugging one
+//! request without correlating logs from every layer.
+//!
+//! Each pipeline stage the request reaches records one entry, in order,
+//! with its duration and a short detail. Details carry sizes, models and
+//! error messages only, never prompt or answer text.
+
+use crate::bindings::exports::wasmcloud::ai::response_handler as wit;
+
+#[derive(Debug)]
+pub struct TraceEntry {
+    pub stage: &'static str,
+    pub ok: bool,
+    pub detail: String,
+    pub duration_us: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Marks the start of a stage.
+    pub fn start() -> u64 {
+        now()
+    }
+
+    /// Records a stage that began at `started`.
+    pub fn record(
+        &mut self,
+        stage: &'static str,
+        started: u64,
+        ok: bool,
+        detail: impl Into<String>,
+    ) {
+        self.entries.push(TraceEntry {
+            stage,
+            ok,
+            detail: detail.into(),
+            duration_us: (now() - started) / 1_000,
+        });
+    }
+
+    pub fn finish(self, output: String) -> TraceReport {
+        TraceReport {
+            entries: self.entries,
+            output,
+        }
+    }
+}
+
+/// Nanoseconds on the monotonic clock. Native builds (the unit tests) have
+/// no WASI host, so they read the standard library's clock instead.
+fn now() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::bindings::wasi::clocks::monotonic_clock::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        EPOCH
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_nanos() as u64
+    }
+}
+
+#[derive(Debug)]
+pub struct TraceReport {
+    pub entries: Vec<TraceEntry>,
+    /// What `prompt-handle` would have returned.
+    pub output: String,
+}
+
+impl From<TraceEntry> for wit::TraceEntry {
+    fn from(entry: TraceEntry) -> Self {
+        Self {
+            stage: entry.stage.to_string(),
+            ok: entry.ok,
+            detail: entry.detail,
+            duration_us: entry.duration_us,
+        }
+    }
+}
+
+impl From<TraceReport> for wit::TraceReport {
+    fn from(report: TraceReport) -> Self {
+        Self {
+            entries: report.entries.into_iter().map(Into::into).collect(),
+            output: report.output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{request_budget, try_handle_prompt};
+
+    const STAGES: [&str; 7] = [
+        "request-middleware",
+        "transport",
+        "decode",
+        "parse",
+        "content-filter",
+        "guard",
+        "response-middleware",
+    ];
+
+    /// The stages `prompt` records, answered by the mock provider.
+    fn stages(prompt: &str) -> Vec<(&'static str, bool)> {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        let mut trace = Trace::default();
+        let _ =
+            futures::executor::block_on(try_handle_prompt(prompt.to_string(), &mut trace, true));
+        trace
+            .entries
+            .iter()
+            .map(|entry| (entry.stage, entry.ok))
+            .collect()
+    }
+
+    #[test]
+    fn a_successful_request_records_every_stage_once_in_order() {
+        let stages = stages("Hello");
+        let expected: Vec<_> = STAGES.iter().map(|stage| (*stage, true)).collect();
+        assert_eq!(stages, expected);
+    }
+
+    #[test]
+    fn a_failed_stage_is_recorded_once_and_ends_the_trace() {
+        // An invalid structured prompt fails while configuring the request.
+        assert_eq!(
+            stages(r#"{"input": "Hello", "temperature": "hot"}"#),
+            [("request-middleware", false)]
+        );
+        assert_eq!(
+            stages(r#"{"input": "Hello", "max_output_tokens": 0}"#),
+            [("request-middleware", false)]
+        );
+        // A cost ceiling below any call fails the transport stage.
+        assert_eq!(
+            stages(r#"{"input": "Hello", "max_cost_usd": 1e-12}"#),
+            [("request-middleware", true), ("transport", false)]
+        );
+    }
+
+    #[test]
+    fn details_never_hold_prompt_or_answer_text() {
+        std::env::set_var("AI_PROVIDER", "mock");
+        request_budget::reset();
+        let mut trace = Trace::default();
+        let answer = futures::executor::block_on(try_handle_prompt(
+            "secret-prompt-text".to_string(),
+            &mut trace,
+            true,
+        ))
+        .unwrap();
+        assert!(answer.text.contains("secret-prompt-text"));
+        for entry in &trace.entries {
+            assert!(
+                !entry.detail.contains("secret"),
+                "{}: {}",
+                entry.stage,
+                entry.detail
+            );
+        }
+    }
+}
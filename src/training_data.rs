@@ -0,0 +1,128 @@
+//! Synthetic input/output pairs for fine-tuning datasets.
+//!
+//! Pairs are generated with concurrent structured output requests of up to
+//! [`PAIRS_PER_REQUEST`] pairs each. The Batch API is not used: batches
+//! complete asynchronously within hours and need a file upload, which does
+//! not fit a single component invocation.
+
+use anyhow::{ensure, Result};
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured};
+
+/// Upper bound on pairs generated per call.
+const MAX_PAIRS: u32 = 500;
+
+/// Pairs requested from the model in one structured output call.
+const PAIRS_PER_REQUEST: u32 = 25;
+
+#[derive(Debug, Clone, Copy)]
+pub enum TrainingFormat {
+    OpenAiChatJsonl,
+    Alpaca,
+    Sharegpt,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrainingPair {
+    pub input: String,
+    pub output: String,
+}
+
+#[derive(Deserialize)]
+struct TrainingPairs {
+    pairs: Vec<TrainingPair>,
+}
+
+const INSTRUCTIONS: &str = "You are generating supervised fine-tuning data. \
+Write diverse, realistic input/output pairs for the described task: vary topic, length, phrasing, and difficulty, \
+include edge cases, and make every output the ideal response to its input.";
+
+/// Generates `n` training pairs for `task_description`.
+pub async fn generate_training_pairs(
+    task_description: String,
+    n: u32,
+) -> Result<Vec<TrainingPair>> {
+    ensure!(
+        (1..=MAX_PAIRS).contains(&n),
+        "n must be between 1 and {MAX_PAIRS}, got {n}"
+    );
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "pairs": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "input": { "type": "string" },
+                        "output": { "type": "string" }
+                    },
+                    "required": ["input", "output"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["pairs"],
+        "additionalProperties": false
+    });
+
+    let batches = n.div_ceil(PAIRS_PER_REQUEST);
+    let requests = (0..batches).map(|batch| {
+        let count = PAIRS_PER_REQUEST.min(n - batch * PAIRS_PER_REQUEST);
+        let input = format!(
+            "Task: {task_description}\nNumber of pairs: {count}\nBatch {} of {batches}: avoid repeating the examples another batch would produce first.",
+            batch + 1
+        );
+        let schema = schema.clone();
+        async move {
+            let mut pairs: TrainingPairs =
+                structured::request("training_pairs", INSTRUCTIONS, &input, schema).await?;
+            pairs.pairs.truncate(count as usize);
+            Ok::<_, anyhow::Error>(pairs.pairs)
+        }
+    });
+
+    Ok(try_join_all(requests)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Serializes `pair` as one dataset record in `format`.
+pub fn serialize(pair: &TrainingPair, format: TrainingFormat, task_description: &str) -> String {
+    let record = match format {
+        TrainingFormat::OpenAiChatJsonl => json!({
+            "messages": [
+                { "role": "user", "content": pair.input },
+                { "role": "assistant", "content": pair.output }
+            ]
+        }),
+        TrainingFormat::Alpaca => json!({
+            "instruction": task_description,
+            "input": pair.input,
+            "output": pair.output
+        }),
+        TrainingFormat::Sharegpt => json!({
+            "conversations": [
+                { "from": "human", "value": pair.input },
+                { "from": "gpt", "value": pair.output }
+            ]
+        }),
+    };
+    record.to_string()
+}
+
+impl From<wit::TrainingFormat> for TrainingFormat {
+    fn from(format: wit::TrainingFormat) -> Self {
+        match format {
+            wit::TrainingFormat::OpenaiChatJsonl => Self::OpenAiChatJsonl,
+            wit::TrainingFormat::Alpaca => Self::Alpaca,
+            wit::TrainingFormat::Sharegpt => Self::Sharegpt,
+        }
+    }
+}
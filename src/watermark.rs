@@ -0,0 +1,212 @@
+//! Invisible watermarks for provenance tracking of generated text.
+//!
+//! Words from a fixed dictionary of synonym pairs are rewritten to the
+//! member of their pair picked by a keyed bit stream. The stream is an
+//! HMAC-SHA256 under the watermark key over a hash of the text with every
+//! dictionary word normalized to the first member of its pair, so the
+//! watermark does not change the hash it is derived from. Verification
+//! recomputes the stream and tests whether the text agrees with it far more
+//! often than the 50% expected by chance.
+
+use std::collections::HashMap;
+
+use crate::sha256;
+
+/// Dictionary words a text needs before a watermark can be detected.
+const MIN_SLOTS: usize = 8;
+
+/// Standard deviations above chance agreement required for detection; 2.5
+/// keeps the false positive rate on unmarked text below 1%.
+const DETECTION_Z_SCORE: f64 = 2.5;
+
+/// Rewrites dictionary words in `text` to embed a watermark for
+/// `watermark_key`. Text with few dictionary words is returned with a
+/// watermark too weak to detect.
+pub fn watermark_response(text: &str, watermark_key: &[u8]) -> String {
+    let dictionary = dictionary();
+    let stream = KeyStream::new(text, watermark_key, &dictionary);
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (slot, (range, (pair, _))) in slots(text, &dictionary).enumerate() {
+        let replacement = if stream.bit(slot) {
+            SYNONYM_PAIRS[pair].1
+        } else {
+            SYNONYM_PAIRS[pair].0
+        };
+        out.push_str(&text[last..range.start]);
+        out.push_str(&match_case(&text[range.clone()], replacement));
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Reports whether `text` carries the watermark for `watermark_key`.
+pub fn verify_watermark(text: &str, watermark_key: &[u8]) -> bool {
+    let dictionary = dictionary();
+    let stream = KeyStream::new(text, watermark_key, &dictionary);
+
+    let (total, matching) = slots(text, &dictionary).enumerate().fold(
+        (0usize, 0usize),
+        |(total, matching), (slot, (_, (_, second)))| {
+            (
+                total + 1,
+                matching + usize::from(stream.bit(slot) == second),
+            )
+        },
+    );
+    if total < MIN_SLOTS {
+        return false;
+    }
+
+    let z = (2.0 * matching as f64 - total as f64) / (total as f64).sqrt();
+    z >= DETECTION_Z_SCORE
+}
+
+/// Maps each dictionary word to its pair index and whether it is the
+/// second member.
+fn dictionary() -> HashMap<&'static str, (usize, bool)> {
+    SYNONYM_PAIRS
+        .iter()
+        .enumerate()
+        .flat_map(|(index, (first, second))| [(*first, (index, false)), (*second, (index, true))])
+        .collect()
+}
+
+/// Byte ranges of dictionary words in `text`, with their dictionary entry.
+fn slots<'a>(
+    text: &'a str,
+    dictionary: &'a HashMap<&'static str, (usize, bool)>,
+) -> impl Iterator<Item = (std::ops::Range<usize>, (usize, bool))> + 'a {
+    words(text).filter_map(move |range| {
+        let word = text[range.clone()].to_ascii_lowercase();
+        dictionary.get(word.as_str()).map(|entry| (range, *entry))
+    })
+}
+
+/// Byte ranges of maximal runs of letters. Non-ASCII bytes count as
+/// letters so that words such as `café` are never split into a dictionary
+/// word; they never match the all-ASCII dictionary themselves.
+fn words(text: &str) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+    let bytes = text.as_bytes();
+    let is_letter = |b: u8| b.is_ascii_alphabetic() || !b.is_ascii();
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        while position < bytes.len() && !is_letter(bytes[position]) {
+            position += 1;
+        }
+        if position == bytes.len() {
+            return None;
+        }
+        let start = position;
+        while position < bytes.len() && is_letter(bytes[position]) {
+            position += 1;
+        }
+        Some(start..position)
+    })
+}
+
+/// Spells `replacement` in the case of `original`.
+fn match_case(original: &str, replacement: &str) -> String {
+    let mut chars = original.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_uppercase() => {
+            if original.len() > 1 && chars.all(|c| c.is_ascii_uppercase()) {
+                replacement.to_ascii_uppercase()
+            } else {
+                let mut out = replacement.to_string();
+                out[..1].make_ascii_uppercase();
+                out
+            }
+        }
+        _ => replacement.to_string(),
+    }
+}
+
+/// The keyed bit stream for one text.
+struct KeyStream<'a> {
+    key: &'a [u8],
+    text_hash: [u8; 32],
+}
+
+impl<'a> KeyStream<'a> {
+    fn new(text: &str, key: &'a [u8], dictionary: &HashMap<&'static str, (usize, bool)>) -> Self {
+        let mut normalized = String::with_capacity(text.len());
+        let mut last = 0;
+        for (range, (pair, _)) in slots(text, dictionary) {
+            normalized.push_str(&text[last..range.start].to_ascii_lowercase());
+            normalized.push_str(SYNONYM_PAIRS[pair].0);
+            last = range.end;
+        }
+        normalized.push_str(&text[last..].to_ascii_lowercase());
+
+        Self {
+            key,
+            text_hash: sha256::digest(normalized.as_bytes()),
+        }
+    }
+
+    /// Whether dictionary word number `slot` should be the second member
+    /// of its pair.
+    fn bit(&self, slot: usize) -> bool {
+        let mut message = self.text_hash.to_vec();
+        message.extend_from_slice(&(slot as u64).to_be_bytes());
+        sha256::hmac(self.key, &message)[0] & 1 == 1
+    }
+}
+
+/// Interchangeable word pairs. Each word appears in exactly one pair.
+#[rustfmt::skip]
+const SYNONYM_PAIRS: [(&str, &str); 200] = [
+    ("big", "large"), ("small", "little"), ("fast", "quick"), ("begin", "start"),
+    ("end", "finish"), ("student", "pupil"), ("buy", "purchase"), ("show", "display"),
+    ("choose", "select"), ("try", "attempt"), ("sofa", "couch"), ("get", "obtain"),
+    ("need", "require"), ("use", "utilize"), ("find", "locate"), ("keep", "retain"),
+    ("maybe", "perhaps"), ("often", "frequently"), ("usually", "typically"), ("mostly", "largely"),
+    ("nearly", "almost"), ("also", "additionally"), ("however", "nevertheless"), ("therefore", "thus"),
+    ("because", "since"), ("enough", "sufficient"), ("important", "significant"), ("hard", "difficult"),
+    ("easy", "simple"), ("clear", "obvious"), ("correct", "accurate"), ("wrong", "incorrect"),
+    ("main", "primary"), ("whole", "entire"), ("rare", "uncommon"), ("common", "frequent"),
+    ("total", "overall"), ("various", "diverse"), ("happy", "glad"), ("sad", "unhappy"),
+    ("angry", "annoyed"), ("scared", "afraid"), ("smart", "clever"), ("strong", "powerful"),
+    ("weak", "feeble"), ("rich", "wealthy"), ("poor", "impoverished"), ("old", "elderly"),
+    ("new", "novel"), ("modern", "contemporary"), ("ancient", "antique"), ("quiet", "silent"),
+    ("loud", "noisy"), ("bright", "vivid"), ("dark", "dim"), ("cold", "chilly"),
+    ("hot", "warm"), ("wet", "damp"), ("dry", "arid"), ("clean", "tidy"),
+    ("dirty", "filthy"), ("strange", "odd"), ("funny", "amusing"), ("boring", "dull"),
+    ("pretty", "attractive"), ("ugly", "unattractive"), ("calm", "peaceful"), ("busy", "occupied"),
+    ("empty", "vacant"), ("broad", "wide"), ("narrow", "slim"), ("deep", "profound"),
+    ("shallow", "superficial"), ("huge", "enormous"), ("rapid", "swift"), ("slow", "sluggish"),
+    ("early", "premature"), ("late", "tardy"), ("last", "final"), ("next", "following"),
+    ("first", "initial"), ("previous", "prior"), ("recent", "latest"), ("unsure", "uncertain"),
+    ("possible", "feasible"), ("likely", "probable"), ("real", "genuine"), ("fake", "counterfeit"),
+    ("exact", "precise"), ("rough", "approximate"), ("basic", "fundamental"), ("key", "crucial"),
+    ("vital", "essential"), ("useful", "helpful"), ("useless", "pointless"), ("cheap", "inexpensive"),
+    ("costly", "expensive"), ("safe", "secure"), ("risky", "dangerous"), ("honest", "truthful"),
+    ("rude", "impolite"), ("brave", "courageous"), ("shy", "timid"), ("lazy", "idle"),
+    ("eager", "keen"), ("proud", "pleased"), ("famous", "renowned"), ("unknown", "obscure"),
+    ("fix", "repair"), ("build", "construct"), ("destroy", "demolish"), ("ask", "inquire"),
+    ("answer", "reply"), ("tell", "inform"), ("talk", "speak"), ("shout", "yell"),
+    ("look", "glance"), ("watch", "observe"), ("hear", "listen"), ("think", "believe"),
+    ("know", "understand"), ("learn", "study"), ("teach", "instruct"), ("remember", "recall"),
+    ("forget", "overlook"), ("like", "enjoy"), ("hate", "detest"), ("want", "desire"),
+    ("hope", "wish"), ("fear", "dread"), ("allow", "permit"), ("stop", "halt"),
+    ("continue", "proceed"), ("change", "alter"), ("grow", "expand"), ("rise", "increase"),
+    ("fall", "decrease"), ("add", "include"), ("remove", "eliminate"), ("join", "connect"),
+    ("split", "divide"), ("send", "transmit"), ("receive", "accept"), ("give", "provide"),
+    ("take", "grab"), ("hold", "grip"), ("carry", "transport"), ("move", "shift"),
+    ("stay", "remain"), ("leave", "depart"), ("arrive", "reach"), ("return", "revert"),
+    ("enter", "access"), ("travel", "journey"), ("walk", "stroll"), ("run", "sprint"),
+    ("jump", "leap"), ("climb", "ascend"), ("throw", "toss"), ("pull", "drag"),
+    ("push", "shove"), ("cut", "trim"), ("close", "shut"), ("open", "unlock"),
+    ("hide", "conceal"), ("reveal", "disclose"), ("gather", "collect"), ("spread", "scatter"),
+    ("fill", "load"), ("wait", "pause"), ("hurry", "rush"), ("idea", "notion"),
+    ("goal", "aim"), ("job", "task"), ("problem", "issue"), ("reason", "cause"),
+    ("result", "outcome"), ("effect", "impact"), ("way", "method"), ("part", "portion"),
+    ("piece", "fragment"), ("area", "region"), ("place", "location"), ("road", "street"),
+    ("car", "automobile"), ("child", "kid"), ("friend", "companion"), ("enemy", "foe"),
+    ("boss", "supervisor"), ("worker", "employee"), ("doctor", "physician"), ("lawyer", "attorney"),
+    ("money", "cash"), ("error", "mistake"), ("fault", "defect"), ("danger", "hazard"),
+    ("chance", "opportunity"), ("trip", "voyage"), ("rule", "regulation"), ("law", "statute"),
+];
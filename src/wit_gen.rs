@@ -0,0 +1,75 @@
+//! WIT interface definitions generated from Rust type definitions.
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::structured;
+
+#[derive(Deserialize)]
+struct WitInterface {
+    wit_interface: String,
+}
+
+const INSTRUCTIONS: &str = "You are an expert in the WebAssembly Component Model. \
+Translate the given Rust type definitions into a single WIT interface: structs become records, \
+fieldless enums become enums, enums with data become variants, bitflag types become flags, \
+and `Option`, `Result`, `Vec`, and tuples map to option, result, list, and tuple. \
+Use kebab-case names and WIT primitive types (u8..u64, s8..s64, f32, f64, bool, char, string). \
+Output only WIT, starting with `interface <name> {`.";
+
+/// Asks the model to translate `rust_types` into a WIT interface.
+pub async fn generate_wit_interface(rust_types: String) -> Result<String> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "wit_interface": { "type": "string" }
+        },
+        "required": ["wit_interface"],
+        "additionalProperties": false
+    });
+
+    let generated: WitInterface =
+        structured::request("wit_interface", INSTRUCTIONS, &rust_types, schema).await?;
+
+    check_syntax(&generated.wit_interface)?;
+
+    Ok(generated.wit_interface)
+}
+
+/// Light syntax check: an `interface` block with balanced braces and no
+/// leftover Rust syntax. This is not a WIT parser.
+fn check_syntax(wit: &str) -> Result<()> {
+    let code: Vec<&str> = wit
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .collect();
+
+    ensure!(
+        code.iter()
+            .any(|line| line.starts_with("interface ") && line.ends_with('{')),
+        "WIT output must declare an `interface <name> {{` block"
+    );
+
+    let mut depth = 0i32;
+    for c in wit.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        ensure!(depth >= 0, "WIT output has an unmatched closing brace");
+    }
+    ensure!(depth == 0, "WIT output has an unclosed brace");
+
+    for rust in ["struct ", "pub ", "fn ", "impl ", "#["] {
+        ensure!(
+            !code.iter().any(|line| line.starts_with(rust)),
+            "WIT output contains Rust syntax `{}`",
+            rust.trim()
+        );
+    }
+
+    Ok(())
+}
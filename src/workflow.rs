@@ -0,0 +1,162 @@
+//! Workflow definitions for AWS Step Functions, Temporal, or Argo generated
+//! from a description.
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{bindings::exports::wasmcloud::ai::response_handler as wit, structured, yaml};
+
+#[derive(Debug, Clone, Copy)]
+pub enum WorkflowEngine {
+    AwsStepFunctions,
+    Temporal,
+    Argo,
+}
+
+impl WorkflowEngine {
+    fn format_hint(self) -> &'static str {
+        match self {
+            Self::AwsStepFunctions => {
+                "an AWS Step Functions state machine in Amazon States Language JSON \
+                 (with StartAt and States)"
+            }
+            Self::Temporal => {
+                "a Temporal workflow definition as JSON describing the workflow name, \
+                 task queue, activities with their retry policies, and the order they run in"
+            }
+            Self::Argo => {
+                "an Argo Workflows manifest in YAML (apiVersion argoproj.io/v1alpha1, \
+                 kind Workflow) using spaces for indentation"
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WorkflowDefinition {
+    definition: String,
+}
+
+const INSTRUCTIONS: &str = "You are a workflow automation engineer. \
+Translate the described process into a complete, deployable workflow definition for the requested engine. \
+Put the whole definition, and nothing else, into the definition field.";
+
+/// Asks the model for a workflow definition targeting `workflow_engine` and
+/// checks that it is well-formed for that engine's format.
+pub async fn generate_workflow(
+    description: String,
+    workflow_engine: WorkflowEngine,
+) -> Result<String> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "definition": { "type": "string" }
+        },
+        "required": ["definition"],
+        "additionalProperties": false
+    });
+
+    let input = format!(
+        "Engine output format: {}\nProcess description: {description}",
+        workflow_engine.format_hint()
+    );
+    let workflow: WorkflowDefinition =
+        structured::request("workflow_definition", INSTRUCTIONS, &input, schema).await?;
+
+    match workflow_engine {
+        WorkflowEngine::AwsStepFunctions | WorkflowEngine::Temporal => {
+            serde_json::from_str::<Value>(&workflow.definition)
+                .context("workflow definition is not valid JSON")?;
+        }
+        WorkflowEngine::Argo => check_argo_yaml(&workflow.definition)?,
+    }
+
+    Ok(workflow.definition)
+}
+
+/// Checks that an Argo manifest is YAML (not JSON) that parses as a mapping
+/// with string `apiVersion` under `argoproj.io` and a string `kind`.
+fn check_argo_yaml(definition: &str) -> Result<()> {
+    let trimmed = definition.trim_start();
+    ensure!(!trimmed.is_empty(), "workflow definition is empty");
+    if trimmed.starts_with('{') {
+        bail!("Argo workflow definition must be YAML, got JSON");
+    }
+
+    let manifest = yaml::parse(definition).context("workflow definition is not valid YAML")?;
+    ensure!(
+        manifest.is_object(),
+        "Argo workflow definition must be a YAML mapping"
+    );
+    for key in ["apiVersion", "kind"] {
+        ensure!(
+            manifest[key]
+                .as_str()
+                .is_some_and(|value| !value.is_empty()),
+            "Argo workflow definition is missing top-level `{key}`"
+        );
+    }
+    let api_version = manifest["apiVersion"].as_str().unwrap_or_default();
+    ensure!(
+        api_version.starts_with("argoproj.io/"),
+        "Argo workflow definition has apiVersion {api_version:?}, expected argoproj.io/..."
+    );
+
+    Ok(())
+}
+
+impl From<wit::WorkflowEngine> for WorkflowEngine {
+    fn from(engine: wit::WorkflowEngine) -> Self {
+        match engine {
+            wit::WorkflowEngine::AwsStepFunctions => Self::AwsStepFunctions,
+            wit::WorkflowEngine::Temporal => Self::Temporal,
+            wit::WorkflowEngine::Argo => Self::Argo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_a_parsed_argo_manifest() {
+        let manifest =
+            "apiVersion: argoproj.io/v1alpha1\nkind: Workflow\nspec:\n  entrypoint: main\n";
+        check_argo_yaml(manifest).unwrap();
+    }
+
+    #[test]
+    fn rejects_manifests_that_are_not_argo_yaml() {
+        let cases = [
+            ("", "empty"),
+            ("{\"apiVersion\": \"argoproj.io/v1alpha1\"}", "got JSON"),
+            (
+                "apiVersion: argoproj.io/v1alpha1\nkind: Workflow\n\tspec: {}\n",
+                "not valid YAML",
+            ),
+            (
+                "apiVersion: argoproj.io/v1alpha1\nkind: [Workflow\n",
+                "not valid YAML",
+            ),
+            (
+                "- apiVersion: argoproj.io/v1alpha1\n",
+                "must be a YAML mapping",
+            ),
+            (
+                "apiVersion: argoproj.io/v1alpha1\nspec: {}\n",
+                "missing top-level `kind`",
+            ),
+            ("apiVersion: v1\nkind: Pod\n", "expected argoproj.io"),
+            (
+                "metadata:\n  apiVersion: argoproj.io/v1alpha1\n  kind: Workflow\n",
+                "missing top-level `apiVersion`",
+            ),
+        ];
+        for (manifest, expected) in cases {
+            let error = format!("{:#}", check_argo_yaml(manifest).unwrap_err());
+            assert!(error.contains(expected), "{manifest:?}: {error}");
+        }
+    }
+}
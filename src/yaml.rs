@@ -0,0 +1,730 @@
+//! Minimal YAML reader for checking generated manifests.
+//!
+//! Covers the block style that Kubernetes-style manifests use: mappings,
+//! sequences (including `- key: value` items), plain and quoted scalars,
+//! `|` and `>` block scalars, single-line flow collections, and comments.
+//! Anchors, aliases, tags, multi-line plain scalars, and multiple documents
+//! are rejected as unsupported rather than misread. Scalars are resolved
+//! with the YAML 1.2 core schema into [`serde_json::Value`].
+
+use anyhow::{bail, Result};
+use serde_json::{Map, Number, Value};
+
+/// Parses a single YAML document.
+pub fn parse(text: &str) -> Result<Value> {
+    let mut parser = Parser::new(text)?;
+    parser.skip_blank();
+    let value = match parser.peek_indent() {
+        Some(indent) => parser.node(indent, None)?,
+        None => Value::Null,
+    };
+    parser.skip_blank();
+    if let Some(line) = parser.lines.get(parser.pos) {
+        bail!(
+            "invalid YAML at line {}: unexpected indentation",
+            line.number
+        );
+    }
+    Ok(value)
+}
+
+struct Line {
+    /// 1-based line number in the input.
+    number: usize,
+    text: String,
+}
+
+impl Line {
+    fn indent(&self) -> usize {
+        self.text.len() - self.text.trim_start_matches(' ').len()
+    }
+
+    fn content(&self) -> &str {
+        &self.text[self.indent()..]
+    }
+
+    fn is_blank(&self) -> bool {
+        let content = strip_comment(self.content()).trim();
+        content.is_empty()
+    }
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Result<Self> {
+        let mut lines = Vec::new();
+        let mut started = false;
+        for (index, text) in text.lines().enumerate() {
+            let number = index + 1;
+            let text = text.trim_end_matches('\r');
+            let indent = &text[..text.len() - text.trim_start_matches([' ', '\t']).len()];
+            if indent.contains('\t') && !text.trim().is_empty() {
+                bail!("invalid YAML at line {number}: indented with tabs");
+            }
+            if !started {
+                let trimmed = text.trim();
+                if trimmed.starts_with('%') {
+                    continue;
+                }
+                if text.trim_end() == "---" {
+                    started = true;
+                    continue;
+                }
+                if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                    started = true;
+                }
+            }
+            if text.trim_end() == "..." {
+                break;
+            }
+            if started && text.trim_end() == "---" {
+                bail!("invalid YAML at line {number}: multiple documents are not supported");
+            }
+            lines.push(Line {
+                number,
+                text: text.to_string(),
+            });
+        }
+        Ok(Self { lines, pos: 0 })
+    }
+
+    fn skip_blank(&mut self) {
+        while self.pos < self.lines.len() && self.lines[self.pos].is_blank() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek_indent(&mut self) -> Option<usize> {
+        self.skip_blank();
+        self.lines.get(self.pos).map(Line::indent)
+    }
+
+    /// Parses the node starting on the current line at `indent`. `parent` is
+    /// the indent of the enclosing mapping key or sequence entry, which block
+    /// scalars must be indented past.
+    fn node(&mut self, indent: usize, parent: Option<usize>) -> Result<Value> {
+        let line = &self.lines[self.pos];
+        let content = strip_comment(line.content()).trim_end();
+        if content == "-" || content.starts_with("- ") {
+            return self.sequence(indent);
+        }
+        if split_key(content).is_some() {
+            return self.mapping(indent);
+        }
+        let number = line.number;
+        let content = content.to_string();
+        self.pos += 1;
+        if content.starts_with(['|', '>']) {
+            return self.block_scalar(&content, parent, number);
+        }
+        inline_value(&content, number)
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value> {
+        let mut map = Map::new();
+        while let Some(line_indent) = self.peek_indent() {
+            if line_indent < indent {
+                break;
+            }
+            let line = &self.lines[self.pos];
+            let number = line.number;
+            if line_indent > indent {
+                bail!("invalid YAML at line {number}: unexpected indentation");
+            }
+            let content = strip_comment(line.content()).trim_end();
+            if content == "-" || content.starts_with("- ") {
+                break;
+            }
+            let Some((key, rest)) = split_key(content) else {
+                bail!("invalid YAML at line {number}: expected `key: value`");
+            };
+            let key = scalar_key(key, number)?;
+            let rest = rest.trim().to_string();
+            self.pos += 1;
+
+            let value = if rest.starts_with(['|', '>']) {
+                self.block_scalar(&rest, Some(indent), number)?
+            } else if !rest.is_empty() {
+                inline_value(&rest, number)?
+            } else {
+                match self.peek_indent() {
+                    Some(next) if next > indent => self.node(next, Some(indent))?,
+                    Some(next) if next == indent && self.at_sequence_entry() => {
+                        self.sequence(indent)?
+                    }
+                    _ => Value::Null,
+                }
+            };
+            if map.insert(key.clone(), value).is_some() {
+                bail!("invalid YAML at line {number}: duplicate key {key:?}");
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn at_sequence_entry(&self) -> bool {
+        let content = strip_comment(self.lines[self.pos].content()).trim_end();
+        content == "-" || content.starts_with("- ")
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value> {
+        let mut items = Vec::new();
+        while let Some(line_indent) = self.peek_indent() {
+            if line_indent != indent || !self.at_sequence_entry() {
+                if line_indent > indent {
+                    let number = self.lines[self.pos].number;
+                    bail!("invalid YAML at line {number}: unexpected indentation");
+                }
+                break;
+            }
+            let line = &mut self.lines[self.pos];
+            let after_dash = &line.text[indent + 1..];
+            if strip_comment(after_dash).trim().is_empty() {
+                self.pos += 1;
+                let item = match self.peek_indent() {
+                    Some(next) if next > indent => self.node(next, Some(indent))?,
+                    _ => Value::Null,
+                };
+                items.push(item);
+                continue;
+            }
+            // Re-read the rest of the entry as a node indented past the dash,
+            // so `- key: value` starts a mapping at the column of `key`.
+            let spaces = 1 + after_dash.len() - after_dash.trim_start_matches(' ').len();
+            let item_indent = indent + spaces;
+            line.text = format!("{}{}", " ".repeat(item_indent), after_dash.trim_start());
+            items.push(self.node(item_indent, Some(indent))?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn block_scalar(
+        &mut self,
+        header: &str,
+        parent: Option<usize>,
+        number: usize,
+    ) -> Result<Value> {
+        let style = header.as_bytes()[0];
+        let mut keep = false;
+        let mut strip = false;
+        let mut explicit_indent = None;
+        for c in header[1..].trim_end().chars() {
+            match c {
+                '-' => strip = true,
+                '+' => keep = true,
+                '1'..='9' => explicit_indent = c.to_digit(10).map(|d| d as usize),
+                _ => bail!("invalid YAML at line {number}: bad block scalar header {header:?}"),
+            }
+        }
+
+        let min_indent = parent.map_or(0, |parent| parent + 1);
+        let mut content_indent = explicit_indent.map(|d| parent.unwrap_or(0) + d);
+        let mut lines = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.text.trim().is_empty() {
+                lines.push(String::new());
+                self.pos += 1;
+                continue;
+            }
+            let indent = line.indent();
+            let needed = *content_indent.get_or_insert(indent.max(min_indent));
+            if indent < needed || indent < min_indent {
+                break;
+            }
+            lines.push(line.text[needed..].to_string());
+            self.pos += 1;
+        }
+
+        let trailing = lines.iter().rev().take_while(|l| l.is_empty()).count();
+        let body = &lines[..lines.len() - trailing];
+        let mut text = if style == b'|' {
+            body.join("\n")
+        } else {
+            fold(body)
+        };
+        if !body.is_empty() && !strip {
+            text.push('\n');
+            if keep {
+                text.push_str(&"\n".repeat(trailing));
+            }
+        }
+        Ok(Value::String(text))
+    }
+}
+
+/// Folds the lines of a `>` scalar: single line breaks between text become
+/// spaces, blank lines become line breaks, and more-indented lines are kept.
+fn fold(lines: &[String]) -> String {
+    let mut text = String::new();
+    let mut previous_text = false;
+    for line in lines {
+        if line.is_empty() {
+            text.push('\n');
+            previous_text = false;
+        } else if line.starts_with(' ') {
+            if previous_text {
+                text.push('\n');
+            }
+            text.push_str(line);
+            text.push('\n');
+            previous_text = false;
+        } else {
+            if previous_text {
+                text.push(' ');
+            }
+            text.push_str(line);
+            previous_text = true;
+        }
+    }
+    text.trim_end_matches('\n').to_string()
+}
+
+/// Removes a trailing `#` comment that is outside quotes.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '#' && previous.is_whitespace() => return &text[..index],
+            None if (c == '"' || c == '\'')
+                && (previous.is_whitespace() || "[{,:".contains(previous)) =>
+            {
+                quote = Some(c)
+            }
+            None => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+/// Splits `key: rest` at the first `:` followed by a space or the end of the
+/// line that is outside quotes and flow collections.
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    let bytes = content.as_bytes();
+    for (index, c) in content.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' if index == 0 => quote = Some(c),
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth = depth.saturating_sub(1),
+                ':' if depth == 0 && bytes.get(index + 1).is_none_or(|b| *b == b' ') => {
+                    if index == 0 || content.starts_with(['[', '{']) {
+                        return None;
+                    }
+                    return Some((&content[..index], &content[index + 1..]));
+                }
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+fn scalar_key(key: &str, number: usize) -> Result<String> {
+    match inline_value(key.trim(), number)? {
+        Value::String(key) => Ok(key),
+        Value::Null => Ok("null".to_string()),
+        Value::Array(_) | Value::Object(_) => {
+            bail!("invalid YAML at line {number}: complex keys are not supported")
+        }
+        other => Ok(other.to_string()),
+    }
+}
+
+fn inline_value(text: &str, number: usize) -> Result<Value> {
+    let text = text.trim();
+    let mut chars = Cursor {
+        text,
+        pos: 0,
+        number,
+    };
+    let value = chars.value(false)?;
+    chars.skip_spaces();
+    if chars.pos < text.len() {
+        bail!(
+            "invalid YAML at line {number}: unexpected {:?} after value",
+            &text[chars.pos..]
+        );
+    }
+    Ok(value)
+}
+
+/// Reader for one line's worth of scalar or flow-collection text.
+struct Cursor<'a> {
+    text: &'a str,
+    pos: usize,
+    number: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T> {
+        bail!("invalid YAML at line {}: {message}", self.number)
+    }
+
+    /// Reads a value; inside a flow collection plain scalars end at `,`,
+    /// `]`, `}`, and `: `.
+    fn value(&mut self, in_flow: bool) -> Result<Value> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => self.double_quoted().map(Value::String),
+            Some('\'') => self.single_quoted().map(Value::String),
+            Some('[') => self.flow_sequence(),
+            Some('{') => self.flow_mapping(),
+            Some('&' | '*' | '!') => self.error("anchors, aliases, and tags are not supported"),
+            Some('|' | '>') if in_flow => {
+                self.error("block scalars are not allowed in flow collections")
+            }
+            _ => Ok(resolve(self.plain(in_flow))),
+        }
+    }
+
+    fn plain(&mut self, in_flow: bool) -> &str {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let mut end = rest.len();
+        for (index, c) in rest.char_indices() {
+            let next = rest[index + c.len_utf8()..].chars().next();
+            let ends = match c {
+                ',' | ']' | '}' => in_flow,
+                ':' => next.is_none_or(|n| n == ' ' || (in_flow && ",]}".contains(n))),
+                _ => false,
+            };
+            if ends {
+                end = index;
+                break;
+            }
+        }
+        self.pos = start + end;
+        rest[..end].trim_end()
+    }
+
+    fn double_quoted(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += index + 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    let Some((_, escape)) = chars.next() else {
+                        break;
+                    };
+                    match escape {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        '0' => out.push('\0'),
+                        '"' | '\\' | '/' | ' ' => out.push(escape),
+                        'x' | 'u' | 'U' => {
+                            let len = match escape {
+                                'x' => 2,
+                                'u' => 4,
+                                _ => 8,
+                            };
+                            let hex: String = chars.by_ref().take(len).map(|(_, c)| c).collect();
+                            let decoded =
+                                u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                            match decoded {
+                                Some(c) if hex.len() == len => out.push(c),
+                                _ => return self.error(&format!("bad escape \\{escape}{hex}")),
+                            }
+                        }
+                        other => return self.error(&format!("unknown escape \\{other}")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        self.error("unterminated double-quoted string")
+    }
+
+    fn single_quoted(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.text[self.pos..].char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().map(|(_, c)| *c) == Some('\'') {
+                    chars.next();
+                    out.push('\'');
+                    continue;
+                }
+                self.pos += index + 1;
+                return Ok(out);
+            }
+            out.push(c);
+        }
+        self.error("unterminated single-quoted string")
+    }
+
+    fn flow_sequence(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                None => return self.error("unterminated flow sequence"),
+                _ => {}
+            }
+            items.push(self.value(true)?);
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                None => return self.error("unterminated flow sequence"),
+                _ => return self.error("expected `,` or `]` in flow sequence"),
+            }
+        }
+    }
+
+    fn flow_mapping(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(map));
+                }
+                None => return self.error("unterminated flow mapping"),
+                _ => {}
+            }
+            let key = match self.value(true)? {
+                Value::String(key) => key,
+                Value::Array(_) | Value::Object(_) => {
+                    return self.error("complex keys are not supported")
+                }
+                other => other.to_string(),
+            };
+            self.skip_spaces();
+            let value = if self.peek() == Some(':') {
+                self.pos += 1;
+                self.skip_spaces();
+                if matches!(self.peek(), Some(',' | '}')) {
+                    Value::Null
+                } else {
+                    self.value(true)?
+                }
+            } else {
+                Value::Null
+            };
+            if map.insert(key.clone(), value).is_some() {
+                return self.error(&format!("duplicate key {key:?}"));
+            }
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {}
+                None => return self.error("unterminated flow mapping"),
+                _ => return self.error("expected `,` or `}` in flow mapping"),
+            }
+        }
+    }
+}
+
+/// Resolves a plain scalar with the YAML 1.2 core schema.
+fn resolve(plain: &str) -> Value {
+    match plain {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    let digits = plain.strip_prefix(['-', '+']).unwrap_or(plain);
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(n) = plain.parse::<i64>() {
+            return Value::Number(n.into());
+        }
+    }
+    for (prefix, radix) in [("0x", 16), ("0o", 8)] {
+        if let Some(n) = plain
+            .strip_prefix(prefix)
+            .and_then(|d| i64::from_str_radix(d, radix).ok())
+        {
+            return Value::Number(n.into());
+        }
+    }
+    let looks_numeric = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && digits.bytes().any(|b| b.is_ascii_digit())
+        && digits
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"eE.+-".contains(&b));
+    if looks_numeric {
+        if let Some(n) = plain.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(plain.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_an_argo_manifest() {
+        let manifest = r#"# generated
+apiVersion: argoproj.io/v1alpha1
+kind: Workflow
+metadata:
+  generateName: hello-world-   # prefix
+spec:
+  entrypoint: main
+  arguments:
+    parameters:
+    - name: message
+      value: "hello: world"
+  templates:
+  - name: main
+    steps:
+    - - name: greet
+        template: whalesay
+        arguments: {parameters: [{name: message, value: "{{workflow.parameters.message}}"}]}
+  - name: whalesay
+    container:
+      image: docker/whalesay:latest
+      command: [cowsay]
+      args: ['it''s {{inputs.parameters.message}}']
+      resources:
+        limits: {cpu: 0.5, memory: 64Mi}
+    script:
+      source: |
+        echo one
+          indented
+        echo two
+    retryStrategy:
+      limit: 3
+      backoff: ~
+"#;
+        let parsed = parse(manifest).unwrap();
+        assert_eq!(parsed["apiVersion"], "argoproj.io/v1alpha1");
+        assert_eq!(parsed["metadata"]["generateName"], "hello-world-");
+        assert_eq!(
+            parsed["spec"]["arguments"]["parameters"],
+            json!([{"name": "message", "value": "hello: world"}])
+        );
+        let templates = &parsed["spec"]["templates"];
+        assert_eq!(templates[0]["steps"][0][0]["template"], "whalesay");
+        assert_eq!(
+            templates[0]["steps"][0][0]["arguments"]["parameters"][0]["value"],
+            "{{workflow.parameters.message}}"
+        );
+        let container = &templates[1]["container"];
+        assert_eq!(container["image"], "docker/whalesay:latest");
+        assert_eq!(container["command"], json!(["cowsay"]));
+        assert_eq!(
+            container["args"],
+            json!(["it's {{inputs.parameters.message}}"])
+        );
+        assert_eq!(
+            container["resources"]["limits"],
+            json!({"cpu": 0.5, "memory": "64Mi"})
+        );
+        assert_eq!(
+            templates[1]["script"]["source"],
+            "echo one\n  indented\necho two\n"
+        );
+        assert_eq!(
+            templates[1]["retryStrategy"],
+            json!({"limit": 3, "backoff": null})
+        );
+    }
+
+    #[test]
+    fn resolves_core_schema_scalars() {
+        let parsed = parse(
+            "a: 12\nb: -3\nc: 1.5e3\nd: true\ne: Null\nf: 0x1F\ng: 1.2.3\nh: \"12\"\ni: yes\nj:\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            json!({"a": 12, "b": -3, "c": 1500.0, "d": true, "e": null, "f": 31,
+                   "g": "1.2.3", "h": "12", "i": "yes", "j": null})
+        );
+    }
+
+    #[test]
+    fn reads_block_scalar_styles() {
+        let parsed = parse(
+            "literal: |-\n  a\n  b\n\nfolded: >\n  one\n  two\n\n  three\nkeep: |+\n  x\n\n\nend: 1\n",
+        )
+        .unwrap();
+        assert_eq!(parsed["literal"], "a\nb");
+        assert_eq!(parsed["folded"], "one two\nthree\n");
+        assert_eq!(parsed["keep"], "x\n\n\n");
+        assert_eq!(parsed["end"], 1);
+    }
+
+    #[test]
+    fn reads_sequences_at_key_indent_and_quoted_escapes() {
+        let parsed =
+            parse("items:\n- 1\n- \"tab\\there \\u00e9\"\n-\n  - nested\nnext: x\n").unwrap();
+        assert_eq!(
+            parsed,
+            json!({"items": [1, "tab\there é", ["nested"]], "next": "x"})
+        );
+    }
+
+    #[test]
+    fn accepts_document_markers_and_empty_documents() {
+        assert_eq!(parse("---\na: 1\n...\n").unwrap(), json!({"a": 1}));
+        assert_eq!(parse("# only a comment\n").unwrap(), Value::Null);
+        assert_eq!(parse("plain text").unwrap(), json!("plain text"));
+    }
+
+    #[test]
+    fn rejects_invalid_and_unsupported_yaml() {
+        let cases = [
+            ("a: 1\n\tb: 2\n", "line 2: indented with tabs"),
+            ("a: 1\n   b: 2\n", "line 2: unexpected indentation"),
+            ("a: 1\na: 2\n", "duplicate key \"a\""),
+            ("a: \"open\n", "unterminated double-quoted string"),
+            ("a: [1, 2\n", "unterminated flow sequence"),
+            ("a: &x 1\n", "anchors, aliases, and tags are not supported"),
+            ("a: 1\n---\nb: 2\n", "multiple documents are not supported"),
+            ("a: 1\n- b\n", "line 2"),
+            ("a: 'x' y\n", "unexpected"),
+        ];
+        for (input, expected) in cases {
+            let error = parse(input).unwrap_err().to_string();
+            assert!(error.contains(expected), "{input:?}: {error}");
+        }
+    }
+}